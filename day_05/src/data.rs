@@ -1,8 +1,9 @@
+use std::collections::HashMap;
 use std::str::FromStr;
 
-#[derive(Eq, PartialEq, Hash, Debug)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone, Debug)]
 // Coord(x, y)
-pub struct Coord(usize, usize);
+pub struct Coord(pub isize, pub isize);
 
 impl FromStr for Coord {
     type Err = ();
@@ -34,41 +35,39 @@ impl FromStr for VentLine {
 
 impl VentLine {
     pub fn coords(&self) -> Vec<Coord> {
-        let mut coords = vec![];
+        let dx = (self.end.0 - self.start.0).signum();
+        let dy = (self.end.1 - self.start.1).signum();
+        let n_coords = (self.end.0 - self.start.0)
+            .abs()
+            .max((self.end.1 - self.start.1).abs());
+
+        let mut coords: Vec<Coord> = (0..=n_coords)
+            .map(|i| Coord(self.start.0 + dx * i, self.start.1 + dy * i))
+            .collect();
+
+        // Non-diagonal lines are always listed from the lowest coordinate up
+        if !self.is_diagonal() {
+            coords.sort();
+        }
+
+        coords
+    }
+
+    // Whether the given point lies on this line
+    #[allow(dead_code)]
+    pub fn contains(&self, coord: &Coord) -> bool {
+        let (min_x, max_x) = (self.start.0.min(self.end.0), self.start.0.max(self.end.0));
+        let (min_y, max_y) = (self.start.1.min(self.end.1), self.start.1.max(self.end.1));
 
-        let min_x = self.start.0.min(self.end.0);
-        let max_x = self.start.0.max(self.end.0);
-        let min_y = self.start.1.min(self.end.1);
-        let max_y = self.start.1.max(self.end.1);
+        if coord.0 < min_x || coord.0 > max_x || coord.1 < min_y || coord.1 > max_y {
+            return false;
+        }
 
         if self.is_diagonal() {
-            let x_increases = self.start.0 < self.end.0;
-            let y_increases = self.start.1 < self.end.1;
-            let n_coords = max_x - min_x;
-
-            for i in 0..=n_coords {
-                match (x_increases, y_increases) {
-                    (true, true) => coords.push(Coord(self.start.0 + i, self.start.1 + i)),
-                    (true, false) => coords.push(Coord(self.start.0 + i, self.start.1 - i)),
-                    (false, true) => coords.push(Coord(self.start.0 - i, self.start.1 + i)),
-                    (false, false) => coords.push(Coord(self.start.0 - i, self.start.1 - i)),
-                }
-            }
+            (coord.0 - self.start.0).abs() == (coord.1 - self.start.1).abs()
         } else {
-            if self.start.0 == self.end.0 {
-                // Constant x, non-diagonal
-                for y in min_y..=max_y {
-                    coords.push(Coord(self.start.0, y));
-                }
-            } else {
-                // Constant y, non-diagonal
-                for x in min_x..=max_x {
-                    coords.push(Coord(x, self.start.1));
-                }
-            }
+            true
         }
-
-        coords
     }
 
     pub fn is_diagonal(&self) -> bool {
@@ -76,6 +75,71 @@ impl VentLine {
     }
 }
 
+// All vent lines, with the number of lines covering each point
+pub struct VentField {
+    #[allow(dead_code)]
+    lines: Vec<VentLine>,
+    counts: HashMap<Coord, usize>,
+}
+
+impl VentField {
+    pub fn new(vent_lines: Vec<VentLine>, skip_diagonal: bool) -> VentField {
+        let lines: Vec<VentLine> = vent_lines
+            .into_iter()
+            .filter(|vent_line| !(skip_diagonal && vent_line.is_diagonal()))
+            .collect();
+
+        let mut counts = HashMap::new();
+        for coord in lines.iter().flat_map(|vent_line| vent_line.coords()) {
+            *counts.entry(coord).or_insert(0) += 1;
+        }
+
+        VentField { lines, counts }
+    }
+
+    #[allow(dead_code)]
+    pub fn overlap_count_at(&self, coord: &Coord) -> usize {
+        self.counts.get(coord).copied().unwrap_or(0)
+    }
+
+    // Every point covered by at least `threshold` lines, in sorted order
+    pub fn points_with_at_least(&self, threshold: usize) -> Vec<Coord> {
+        let mut points: Vec<Coord> = self
+            .counts
+            .iter()
+            .filter(|(_, &count)| count >= threshold)
+            .map(|(&coord, _)| coord)
+            .collect();
+        points.sort();
+        points
+    }
+
+    #[allow(dead_code)]
+    pub fn lines_through(&self, coord: &Coord) -> Vec<&VentLine> {
+        self.lines
+            .iter()
+            .filter(|vent_line| vent_line.contains(coord))
+            .collect()
+    }
+
+    // (top-left, bottom-right) corners of the smallest box containing every line, or None if empty
+    #[allow(dead_code)]
+    pub fn bounding_box(&self) -> Option<(Coord, Coord)> {
+        let mut coords = self
+            .lines
+            .iter()
+            .flat_map(|vent_line| [vent_line.start, vent_line.end]);
+        let first = coords.next()?;
+
+        Some(coords.fold((first, first), |(min, max), c| {
+            (
+                Coord(min.0.min(c.0), min.1.min(c.1)),
+                Coord(max.0.max(c.0), max.1.max(c.1)),
+            )
+        }))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -114,4 +178,45 @@ mod tests {
             vec![Coord(9, 7), Coord(8, 8), Coord(7, 9)]
         );
     }
+
+    #[test]
+    fn test_negative_coords() {
+        assert_eq!(
+            "-1,2 -> 1,0".parse::<VentLine>().unwrap().coords(),
+            vec![Coord(-1, 2), Coord(0, 1), Coord(1, 0)]
+        );
+
+        assert_eq!(
+            "-3,-1 -> -1,-1".parse::<VentLine>().unwrap().coords(),
+            vec![Coord(-3, -1), Coord(-2, -1), Coord(-1, -1)]
+        );
+    }
+
+    #[test]
+    fn test_vent_field_queries() {
+        let field = VentField::new(get_test_input(), false);
+
+        assert_eq!(field.overlap_count_at(&Coord(4, 4)), 3);
+        assert_eq!(field.overlap_count_at(&Coord(7, 4)), 2);
+        assert_eq!(field.overlap_count_at(&Coord(0, 0)), 1);
+        assert_eq!(field.overlap_count_at(&Coord(9, 0)), 0);
+
+        assert_eq!(field.points_with_at_least(2).len(), 12);
+        assert_eq!(
+            field.points_with_at_least(3),
+            vec![Coord(4, 4), Coord(6, 4)]
+        );
+
+        assert_eq!(
+            field.lines_through(&Coord(4, 4)),
+            vec![
+                &"8,0 -> 0,8".parse::<VentLine>().unwrap(),
+                &"9,4 -> 3,4".parse::<VentLine>().unwrap(),
+                &"0,0 -> 8,8".parse::<VentLine>().unwrap(),
+            ]
+        );
+
+        assert_eq!(field.bounding_box(), Some((Coord(0, 0), Coord(9, 9))));
+        assert_eq!(VentField::new(vec![], false).bounding_box(), None);
+    }
 }
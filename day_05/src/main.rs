@@ -3,7 +3,6 @@ mod input;
 
 use data::*;
 use input::*;
-use std::collections::HashMap;
 
 fn main() {
     println!("day: 05");
@@ -23,20 +22,9 @@ fn part_2(vent_lines: Vec<VentLine>) -> usize {
 }
 
 fn solve(vent_lines: Vec<VentLine>, skip_diagonal: bool) -> usize {
-    let mut map: HashMap<Coord, usize> = HashMap::new();
-
-    for vent_line in vent_lines {
-        if skip_diagonal && vent_line.is_diagonal() {
-            continue;
-        }
-
-        for coord in vent_line.coords() {
-            let count = map.entry(coord).or_insert(0);
-            *count += 1;
-        }
-    }
-
-    map.values().filter(|&&count| count >= 2).count()
+    VentField::new(vent_lines, skip_diagonal)
+        .points_with_at_least(2)
+        .len()
 }
 
 #[cfg(test)]
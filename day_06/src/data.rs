@@ -1,44 +1,154 @@
-use std::collections::HashMap;
+use std::fmt::Write;
 
-#[derive(Eq, PartialEq, Debug)]
-// HashSet<cycle_day, count>
-pub struct FishState(pub(crate) HashMap<u8, usize>);
+// FishState[cycle_day] = count, for cycle days 0..N
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct FishState<const N: usize = 9>(pub(crate) [usize; N]);
 
-impl FishState {
+impl<const N: usize> FishState<N> {
     pub fn fish_count(&self) -> usize {
-        self.0.values().sum()
+        self.0.iter().sum()
+    }
+
+    // (cycle_day, count) for every cycle day, without copying the state
+    #[allow(dead_code)]
+    pub fn counts(&self) -> impl Iterator<Item = (u8, usize)> + '_ {
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(cycle_day, &count)| (cycle_day as u8, count))
     }
+
+    // Advances this state in place under the given species' rules, yielding the fish count after
+    // each day
+    pub fn days<'a>(&'a mut self, species: &'a Species) -> Days<'a, N> {
+        Days {
+            fish_state: self,
+            species,
+        }
+    }
+
+    // CSV with one row per day (day 0 being this state) of the total and per-cycle-day counts
+    #[allow(dead_code)]
+    pub fn history_csv(&self, species: &Species, days: usize) -> String {
+        let mut csv = String::from("day,total");
+        for cycle_day in 0..N {
+            write!(csv, ",timer_{}", cycle_day).unwrap();
+        }
+        csv.push('\n');
+
+        let mut fish_state = *self;
+        for day in 0..=days {
+            if day > 0 {
+                species.advance(&mut fish_state);
+            }
+
+            write!(csv, "{},{}", day, fish_state.fish_count()).unwrap();
+            for (_, count) in fish_state.counts() {
+                write!(csv, ",{}", count).unwrap();
+            }
+            csv.push('\n');
+        }
+
+        csv
+    }
+}
+
+pub struct Days<'a, const N: usize> {
+    fish_state: &'a mut FishState<N>,
+    species: &'a Species,
 }
 
-impl Iterator for FishState {
-    type Item = FishState;
+impl<'a, const N: usize> Iterator for Days<'a, N> {
+    type Item = usize;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let mut next_state = HashMap::new();
-
-        for (cycle_day, count) in self.0.drain() {
-            if cycle_day == 0 {
-                *next_state.entry(6).or_insert(0) += count;
-                *next_state.entry(8).or_insert(0) += count;
-            } else {
-                *next_state.entry(cycle_day - 1).or_insert(0) += count;
+        self.species.advance(self.fish_state);
+        Some(self.fish_state.fish_count())
+    }
+}
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct Species {
+    // Cycle day a fish returns to after spawning
+    pub reset_timer: u8,
+    // Cycle day newborn fish start at
+    pub newborn_timer: u8,
+    // Newborn fish per spawning fish
+    pub offspring: usize,
+}
+
+impl Species {
+    pub const LANTERNFISH: Species = Species {
+        reset_timer: 6,
+        newborn_timer: 8,
+        offspring: 1,
+    };
+
+    // Moves the given state forward one day under this species' rules. Every timer counts down,
+    // including any above this species' own. Panics if the state has too few cycle days for this
+    // species' timers.
+    pub fn advance<const N: usize>(&self, fish_state: &mut FishState<N>) {
+        let timer_count = self.timer_count();
+        assert!(
+            timer_count <= N,
+            "species needs {} cycle days, state only has {}",
+            timer_count,
+            N
+        );
+
+        let buckets = &mut fish_state.0;
+        let spawning = buckets[0];
+        buckets.rotate_left(1);
+        buckets[N - 1] = 0;
+        buckets[self.reset_timer as usize] += spawning;
+        buckets[self.newborn_timer as usize] += spawning * self.offspring;
+    }
+}
+
+// Several species living side by side, each following its own rules
+#[allow(dead_code)]
+#[derive(Eq, PartialEq, Debug)]
+pub struct Population<const N: usize = 9>(pub Vec<(Species, FishState<N>)>);
+
+#[allow(dead_code)]
+impl<const N: usize> Population<N> {
+    pub fn fish_count(&self) -> usize {
+        self.0
+            .iter()
+            .map(|(_, fish_state)| fish_state.fish_count())
+            .sum()
+    }
+
+    pub fn fish_count_by_species(&self) -> Vec<(Species, usize)> {
+        self.0
+            .iter()
+            .map(|(species, fish_state)| (*species, fish_state.fish_count()))
+            .collect()
+    }
+
+    pub fn advance(&mut self, days: usize) {
+        for _ in 0..days {
+            for (species, fish_state) in self.0.iter_mut() {
+                species.advance(fish_state);
             }
         }
-
-        self.0 = next_state.clone();
-        Some(FishState(next_state))
     }
 }
 
-impl From<Vec<u8>> for FishState {
-    fn from(fish: Vec<u8>) -> Self {
-        let wtf = HashMap::new();
-        let yo = fish.iter().fold(wtf, |mut hm, &d| {
-            let count = hm.entry(d).or_insert(0);
+// Fails if a fish's timer is past the state's last cycle day
+impl<const N: usize> TryFrom<Vec<u8>> for FishState<N> {
+    type Error = String;
+
+    fn try_from(fish: Vec<u8>) -> Result<Self, Self::Error> {
+        let mut counts = [0; N];
+        for cycle_day in fish {
+            let count = counts.get_mut(cycle_day as usize).ok_or(format!(
+                "Timer {} doesn't fit in {} cycle days",
+                cycle_day, N
+            ))?;
             *count += 1;
-            hm
-        });
-        FishState(yo)
+        }
+        Ok(FishState(counts))
     }
 }
 
@@ -48,23 +158,104 @@ mod tests {
     use crate::get_test_input;
 
     #[test]
-    fn test_fish_state_iterator() {
-        let mut fish_state = FishState::from(get_test_input::<u8>());
+    fn test_fish_state_days() {
+        let mut fish_state: FishState = get_test_input::<u8>().try_into().unwrap();
+        assert_eq!(fish_state, FishState([0, 1, 1, 2, 1, 0, 0, 0, 0]));
+
+        let mut days = fish_state.days(&Species::LANTERNFISH);
+        assert_eq!(days.next(), Some(5));
+        assert_eq!(days.next(), Some(6));
+        assert_eq!(fish_state, FishState([1, 2, 1, 0, 0, 0, 1, 0, 1]));
+
+        assert_eq!(fish_state.days(&Species::LANTERNFISH).nth(15), Some(26));
+    }
+
+    #[test]
+    fn test_fish_state_counts() {
+        let fish_state: FishState = get_test_input::<u8>().try_into().unwrap();
         assert_eq!(
-            fish_state,
-            FishState(HashMap::from([(1, 1), (2, 1), (3, 2), (4, 1)]))
+            fish_state
+                .counts()
+                .filter(|&(_, count)| count > 0)
+                .collect::<Vec<_>>(),
+            vec![(1, 1), (2, 1), (3, 2), (4, 1)]
         );
+    }
 
-        let next_fish_state = fish_state.next().unwrap();
+    #[test]
+    fn test_history_csv() {
+        let fish_state: FishState = get_test_input::<u8>().try_into().unwrap();
         assert_eq!(
-            next_fish_state,
-            FishState(HashMap::from([(0, 1), (1, 1), (2, 2), (3, 1)]))
+            fish_state.history_csv(&Species::LANTERNFISH, 2),
+            "day,total,timer_0,timer_1,timer_2,timer_3,timer_4,timer_5,timer_6,timer_7,timer_8\n\
+             0,5,0,1,1,2,1,0,0,0,0\n\
+             1,5,1,1,2,1,0,0,0,0,0\n\
+             2,6,1,2,1,0,0,0,1,0,1\n"
         );
+    }
 
-        let next_fish_state = fish_state.next().unwrap();
+    #[test]
+    fn test_species_advance() {
+        let fast = Species {
+            reset_timer: 2,
+            newborn_timer: 4,
+            offspring: 3,
+        };
+
+        let mut fish_state: FishState<5> = vec![0, 1].try_into().unwrap();
+        fast.advance(&mut fish_state);
+        assert_eq!(fish_state, FishState([1, 0, 1, 0, 3]));
+
+        fast.advance(&mut fish_state);
+        assert_eq!(fish_state, FishState([0, 1, 1, 3, 3]));
+    }
+
+    #[test]
+    fn test_population() {
+        let slow = Species {
+            reset_timer: 10,
+            newborn_timer: 12,
+            offspring: 2,
+        };
+
+        let mut population: Population<13> = Population(vec![
+            (
+                Species::LANTERNFISH,
+                get_test_input::<u8>().try_into().unwrap(),
+            ),
+            (slow, vec![0].try_into().unwrap()),
+        ]);
+        population.advance(18);
+
+        assert_eq!(
+            population.fish_count_by_species(),
+            vec![(Species::LANTERNFISH, 26), (slow, 9)]
+        );
+        assert_eq!(population.fish_count(), 35);
+    }
+
+    #[test]
+    fn test_species_advance_high_timers() {
+        // A fish past the species' own timers still counts down, then joins the normal cycle
+        let mut fish_state: FishState<13> = vec![10].try_into().unwrap();
+        let mut days = fish_state.days(&Species::LANTERNFISH);
+        assert_eq!(days.nth(9), Some(1));
+        assert_eq!(days.next(), Some(2));
+        assert_eq!(
+            fish_state.counts().find(|&(_, count)| count > 0),
+            Some((6, 1))
+        );
+    }
+
+    #[test]
+    fn test_fish_state_try_from() {
+        assert_eq!(
+            FishState::<9>::try_from(vec![3, 8, 3]),
+            Ok(FishState([0, 0, 0, 2, 0, 0, 0, 0, 1]))
+        );
         assert_eq!(
-            next_fish_state,
-            FishState(HashMap::from([(0, 1), (1, 2), (2, 1), (6, 1), (8, 1)]))
+            FishState::<9>::try_from(vec![3, 9]),
+            Err("Timer 9 doesn't fit in 9 cycle days".to_string())
         );
     }
 }
@@ -1,5 +1,6 @@
 mod data;
 mod input;
+mod matrix;
 
 use data::*;
 use input::*;
@@ -12,14 +13,16 @@ fn main() {
 
 // How many lanternfish would there be after 80 days?
 fn part_1(initial_fish: Vec<u8>) -> usize {
-    let fish_state: FishState = initial_fish.into();
-    fish_state.skip(79).next().unwrap().fish_count()
+    let mut fish_state: FishState = initial_fish.try_into().unwrap();
+    fish_state.days(&Species::LANTERNFISH).nth(79).unwrap()
 }
 
 // How many lanternfish would there be after 256 days?
 fn part_2(initial_fish: Vec<u8>) -> usize {
-    let fish_state: FishState = initial_fish.into();
-    fish_state.skip(255).next().unwrap().fish_count()
+    let fish_state: FishState = initial_fish.try_into().unwrap();
+    Species::LANTERNFISH
+        .fish_count_after(&fish_state, 256)
+        .unwrap()
 }
 
 #[cfg(test)]
@@ -0,0 +1,295 @@
+use crate::data::{FishState, Species};
+use num_bigint::BigUint;
+
+// A number type fish can be counted in. Operations return None on overflow.
+pub trait FishCount: Clone + Sized {
+    fn zero() -> Self;
+    fn from_usize(n: usize) -> Self;
+    fn checked_add(&self, other: &Self) -> Option<Self>;
+    fn checked_mul(&self, other: &Self) -> Option<Self>;
+}
+
+impl FishCount for usize {
+    fn zero() -> Self {
+        0
+    }
+
+    fn from_usize(n: usize) -> Self {
+        n
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        usize::checked_add(*self, *other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        usize::checked_mul(*self, *other)
+    }
+}
+
+impl FishCount for BigUint {
+    fn zero() -> Self {
+        BigUint::ZERO
+    }
+
+    fn from_usize(n: usize) -> Self {
+        BigUint::from(n)
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(self + other)
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(self * other)
+    }
+}
+
+// A fish count modulo M, for when only the remainder matters. Never overflows. M can't be 0; using
+// ModCount<0> is a compile error.
+#[allow(dead_code)]
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct ModCount<const M: u64>(pub u64);
+
+impl<const M: u64> ModCount<M> {
+    const MODULUS: u128 = {
+        assert!(M != 0, "ModCount needs a modulus above 0");
+        M as u128
+    };
+}
+
+impl<const M: u64> FishCount for ModCount<M> {
+    fn zero() -> Self {
+        ModCount(0)
+    }
+
+    fn from_usize(n: usize) -> Self {
+        ModCount((n as u128 % Self::MODULUS) as u64)
+    }
+
+    fn checked_add(&self, other: &Self) -> Option<Self> {
+        Some(ModCount(
+            ((self.0 as u128 + other.0 as u128) % Self::MODULUS) as u64,
+        ))
+    }
+
+    fn checked_mul(&self, other: &Self) -> Option<Self> {
+        Some(ModCount(
+            ((self.0 as u128 * other.0 as u128) % Self::MODULUS) as u64,
+        ))
+    }
+}
+
+// Square matrix; Matrix[row][col]
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Matrix<T>(Vec<Vec<T>>);
+
+impl<T: FishCount> Matrix<T> {
+    pub fn identity(size: usize) -> Matrix<T> {
+        Matrix(
+            (0..size)
+                .map(|row| {
+                    (0..size)
+                        .map(|col| T::from_usize((row == col) as usize))
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    pub fn checked_mul(&self, other: &Matrix<T>) -> Option<Matrix<T>> {
+        let size = self.0.len();
+        let mut result = vec![vec![T::zero(); size]; size];
+
+        for (row, result_row) in result.iter_mut().enumerate() {
+            for (col, cell) in result_row.iter_mut().enumerate() {
+                for i in 0..size {
+                    let product = self.0[row][i].checked_mul(&other.0[i][col])?;
+                    *cell = cell.checked_add(&product)?;
+                }
+            }
+        }
+
+        Some(Matrix(result))
+    }
+
+    // Exponentiation by squaring. None if any entry of any intermediate matrix overflows T, which
+    // can happen even when the entries of the result would fit.
+    pub fn checked_pow(&self, mut exp: u64) -> Option<Matrix<T>> {
+        let mut result = Matrix::identity(self.0.len());
+        let mut base = self.clone();
+
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result.checked_mul(&base)?;
+            }
+            exp >>= 1;
+            if exp > 0 {
+                base = base.checked_mul(&base)?;
+            }
+        }
+
+        Some(result)
+    }
+
+    pub fn checked_apply(&self, vector: &[T]) -> Option<Vec<T>> {
+        self.0
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(vector)
+                    .try_fold(T::zero(), |sum, (a, b)| sum.checked_add(&a.checked_mul(b)?))
+            })
+            .collect()
+    }
+}
+
+impl Species {
+    // Number of distinct cycle days a fish of this species can be on
+    pub fn timer_count(&self) -> usize {
+        self.reset_timer.max(self.newborn_timer) as usize + 1
+    }
+
+    // Matrix M where next_day[i] = sum(M[i][j] * today[j]), over `size` cycle days. Panics if that
+    // is too few for this species' timers.
+    pub fn transition_matrix<T: FishCount>(&self, size: usize) -> Matrix<T> {
+        assert!(
+            self.timer_count() <= size,
+            "species needs {} cycle days, matrix only has {}",
+            self.timer_count(),
+            size
+        );
+        let mut matrix = vec![vec![0; size]; size];
+
+        for cycle_day in 1..size {
+            matrix[cycle_day - 1][cycle_day] += 1;
+        }
+        matrix[self.reset_timer as usize][0] += 1;
+        matrix[self.newborn_timer as usize][0] += self.offspring;
+
+        Matrix(
+            matrix
+                .into_iter()
+                .map(|row| row.into_iter().map(T::from_usize).collect())
+                .collect(),
+        )
+    }
+
+    // Fish count after the given number of days in O(log days). None means T overflowed while
+    // raising the transition matrix to that power, not necessarily that the answer doesn't fit: the
+    // matrix entries count descendants of a single fish on each timer, and can outgrow T even when
+    // this state has no fish on the timers that grow fastest. Use BigUint or ModCount to be sure.
+    pub fn fish_count_after<T: FishCount, const N: usize>(
+        &self,
+        fish_state: &FishState<N>,
+        days: u64,
+    ) -> Option<T> {
+        // Cover every cycle day the state has, so fish on timers above this species' own count too
+        let size = N.max(self.timer_count());
+        let today: Vec<T> = (0..size)
+            .map(|cycle_day| T::from_usize(fish_state.0.get(cycle_day).copied().unwrap_or(0)))
+            .collect();
+
+        self.transition_matrix(size)
+            .checked_pow(days)?
+            .checked_apply(&today)?
+            .iter()
+            .try_fold(T::zero(), |sum, count| sum.checked_add(count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_test_input;
+
+    #[test]
+    fn test_fish_count_after() {
+        let fish_state: FishState = get_test_input::<u8>().try_into().unwrap();
+        let lanternfish = Species::LANTERNFISH;
+
+        assert_eq!(
+            lanternfish.fish_count_after::<usize, _>(&fish_state, 0),
+            Some(5)
+        );
+        assert_eq!(
+            lanternfish.fish_count_after::<usize, _>(&fish_state, 18),
+            Some(26)
+        );
+        assert_eq!(
+            lanternfish.fish_count_after::<usize, _>(&fish_state, 256),
+            Some(26984457539)
+        );
+        assert_eq!(
+            lanternfish.fish_count_after::<BigUint, _>(&fish_state, 256),
+            Some(BigUint::from(26984457539_u64))
+        );
+    }
+
+    #[test]
+    fn test_fish_count_after_overflow() {
+        let fish_state: FishState = get_test_input::<u8>().try_into().unwrap();
+        let lanternfish = Species::LANTERNFISH;
+
+        assert_eq!(
+            lanternfish.fish_count_after::<usize, _>(&fish_state, 10_000),
+            None
+        );
+
+        let big = lanternfish
+            .fish_count_after::<BigUint, _>(&fish_state, 10_000)
+            .unwrap();
+        assert!(big > BigUint::from(usize::MAX));
+
+        let modular = lanternfish
+            .fish_count_after::<ModCount<1_000_000_007>, _>(&fish_state, 10_000)
+            .unwrap();
+        assert_eq!(
+            BigUint::from(modular.0),
+            big % BigUint::from(1_000_000_007_u64)
+        );
+
+        assert!(lanternfish
+            .fish_count_after::<ModCount<1_000_000_007>, _>(&fish_state, 1_000_000_000_000)
+            .is_some());
+    }
+
+    #[test]
+    fn test_fish_count_after_high_timers() {
+        let mut fish_state: FishState<13> = vec![10, 12].try_into().unwrap();
+        let lanternfish = Species::LANTERNFISH;
+
+        assert_eq!(
+            lanternfish.fish_count_after::<usize, _>(&fish_state, 0),
+            Some(2)
+        );
+
+        let initial = fish_state;
+        for day in 1..=40 {
+            lanternfish.advance(&mut fish_state);
+            assert_eq!(
+                lanternfish.fish_count_after::<usize, _>(&initial, day),
+                Some(fish_state.fish_count())
+            );
+        }
+    }
+
+    #[test]
+    fn test_fish_count_after_matches_simulation() {
+        let species = Species {
+            reset_timer: 3,
+            newborn_timer: 5,
+            offspring: 2,
+        };
+        let initial: FishState<6> = get_test_input::<u8>().try_into().unwrap();
+        let mut fish_state = initial;
+
+        for day in 1..=40 {
+            species.advance(&mut fish_state);
+            assert_eq!(
+                species.fish_count_after::<usize, _>(&initial, day),
+                Some(fish_state.fish_count())
+            );
+        }
+    }
+}
@@ -0,0 +1,410 @@
+use std::fmt::Write;
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct Alignment {
+    pub position: isize,
+    pub fuel: isize,
+}
+
+// Fuel a single crab spends to move the given (non-negative) distance
+pub trait FuelCost {
+    fn cost(&self, dist: isize) -> isize;
+
+    // Whether cost is convex and never decreasing in dist. If so, total fuel is convex in the end
+    // position, and the optimizer can binary search instead of trying every position. A convex
+    // cost that starts out decreasing doesn't count; optimize() checks for that and scans instead.
+    #[allow(dead_code)]
+    fn is_convex(&self) -> bool {
+        false
+    }
+}
+
+// Each step costs 1
+pub struct LinearCost;
+
+impl FuelCost for LinearCost {
+    fn cost(&self, dist: isize) -> isize {
+        dist
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+}
+
+// Each step costs 1 more than the last
+pub struct TriangularCost;
+
+impl FuelCost for TriangularCost {
+    fn cost(&self, dist: isize) -> isize {
+        dist * (dist + 1) / 2
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+}
+
+#[allow(dead_code)]
+pub struct QuadraticCost;
+
+impl FuelCost for QuadraticCost {
+    fn cost(&self, dist: isize) -> isize {
+        dist * dist
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+}
+
+#[allow(dead_code)]
+pub struct CustomCost<F: Fn(isize) -> isize> {
+    pub cost: F,
+    pub is_convex: bool,
+}
+
+impl<F: Fn(isize) -> isize> FuelCost for CustomCost<F> {
+    fn cost(&self, dist: isize) -> isize {
+        (self.cost)(dist)
+    }
+
+    fn is_convex(&self) -> bool {
+        self.is_convex
+    }
+}
+
+pub fn total_fuel(fuel_cost: &impl FuelCost, positions: &[isize], end_position: isize) -> isize {
+    positions
+        .iter()
+        .map(|p| fuel_cost.cost((p - end_position).abs()))
+        .sum()
+}
+
+// Lowest-fuel end position between the outermost crabs, preferring the lowest position on ties
+#[allow(dead_code)]
+pub fn optimize(fuel_cost: &impl FuelCost, positions: &[isize]) -> Alignment {
+    let min = *positions.iter().min().unwrap();
+    let max = *positions.iter().max().unwrap();
+    let alignment_at = |position| Alignment {
+        position,
+        fuel: total_fuel(fuel_cost, positions, position),
+    };
+
+    // A convex cost that doesn't decrease from 0 to 1 never decreases after that either
+    if fuel_cost.is_convex() && fuel_cost.cost(1) >= fuel_cost.cost(0) {
+        // Find the first position where fuel stops decreasing
+        let (mut low, mut high) = (min, max);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if alignment_at(mid).fuel <= alignment_at(mid + 1).fuel {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        alignment_at(low)
+    } else {
+        (min..=max)
+            .map(alignment_at)
+            .min_by_key(|alignment| (alignment.fuel, alignment.position))
+            .unwrap()
+    }
+}
+
+// Total linear distance is minimized at the median; with an even count, the lower median is the
+// lowest of the optimal positions
+pub fn align_linear(positions: &[isize]) -> Alignment {
+    let mut sorted = positions.to_vec();
+    sorted.sort_unstable();
+    let position = sorted[(sorted.len() - 1) / 2];
+
+    Alignment {
+        position,
+        fuel: total_fuel(&LinearCost, positions, position),
+    }
+}
+
+// Triangular cost is (dist^2 + dist) / 2, so the optimum lies within half a step of the mean; check
+// the integers around it and keep the lowest position on ties
+pub fn align_triangular(positions: &[isize]) -> Alignment {
+    let mean = positions
+        .iter()
+        .sum::<isize>()
+        .div_euclid(positions.len() as isize);
+
+    (mean - 1..=mean + 1)
+        .map(|position| Alignment {
+            position,
+            fuel: total_fuel(&TriangularCost, positions, position),
+        })
+        .min_by_key(|alignment| (alignment.fuel, alignment.position))
+        .unwrap()
+}
+
+// Everything behind an alignment choice: where, how much each crab pays, and what every other
+// position between the outermost crabs would have cost
+#[allow(dead_code)]
+#[derive(Eq, PartialEq, Debug)]
+pub struct FuelReport {
+    pub alignment: Alignment,
+    // (start_position, fuel) for each crab, in input order
+    pub crab_fuel: Vec<(isize, isize)>,
+    pub cost_curve: Vec<Alignment>,
+}
+
+#[allow(dead_code)]
+impl FuelReport {
+    pub fn new(fuel_cost: &impl FuelCost, positions: &[isize]) -> FuelReport {
+        let alignment = optimize(fuel_cost, positions);
+
+        let crab_fuel = positions
+            .iter()
+            .map(|&p| (p, fuel_cost.cost((p - alignment.position).abs())))
+            .collect();
+
+        let min = *positions.iter().min().unwrap();
+        let max = *positions.iter().max().unwrap();
+        let cost_curve = (min..=max)
+            .map(|position| Alignment {
+                position,
+                fuel: total_fuel(fuel_cost, positions, position),
+            })
+            .collect();
+
+        FuelReport {
+            alignment,
+            crab_fuel,
+            cost_curve,
+        }
+    }
+
+    pub fn crab_fuel_csv(&self) -> String {
+        let mut csv = String::from("crab,start_position,end_position,fuel\n");
+        for (crab, (start_position, fuel)) in self.crab_fuel.iter().enumerate() {
+            writeln!(
+                csv,
+                "{},{},{},{}",
+                crab, start_position, self.alignment.position, fuel
+            )
+            .unwrap();
+        }
+        csv
+    }
+
+    pub fn cost_curve_csv(&self) -> String {
+        let mut csv = String::from("position,fuel,optimal\n");
+        for alignment in &self.cost_curve {
+            writeln!(
+                csv,
+                "{},{},{}",
+                alignment.position,
+                alignment.fuel,
+                alignment == &self.alignment
+            )
+            .unwrap();
+        }
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_test_input;
+
+    #[test]
+    fn test_align_linear() {
+        assert_eq!(
+            align_linear(&get_test_input()),
+            Alignment {
+                position: 2,
+                fuel: 37
+            }
+        );
+        assert_eq!(
+            align_linear(&[0, 1_000_000_000_000, 7, -3]),
+            Alignment {
+                position: 0,
+                fuel: 1_000_000_000_010
+            }
+        );
+    }
+
+    #[test]
+    fn test_align_triangular() {
+        assert_eq!(
+            align_triangular(&get_test_input()),
+            Alignment {
+                position: 5,
+                fuel: 168
+            }
+        );
+        assert_eq!(
+            align_triangular(&[-1_000_000, 1_000_000, 3]),
+            Alignment {
+                position: 1,
+                fuel: 1_000_001_000_004
+            }
+        );
+    }
+
+    #[test]
+    fn test_align_matches_brute_force() {
+        let positions = vec![3, 9, 9, 12, 40, 41, 57, 100, -8, 0, 77];
+
+        for (align, fuel) in [
+            (
+                align_linear as fn(&[isize]) -> Alignment,
+                (|positions, position| total_fuel(&LinearCost, positions, position))
+                    as fn(&[isize], isize) -> isize,
+            ),
+            (align_triangular, |positions, position| {
+                total_fuel(&TriangularCost, positions, position)
+            }),
+        ] {
+            let brute_force = (-8..=100)
+                .map(|position| (fuel(&positions, position), position))
+                .min()
+                .unwrap();
+            let alignment = align(&positions);
+            assert_eq!((alignment.fuel, alignment.position), brute_force);
+        }
+    }
+
+    #[test]
+    fn test_align_matches_optimize() {
+        let positions = vec![3, 9, 9, 12, 40, 41, 57, 100, -8, 0, 77];
+        assert_eq!(align_linear(&positions), optimize(&LinearCost, &positions));
+        assert_eq!(
+            align_triangular(&positions),
+            optimize(&TriangularCost, &positions)
+        );
+    }
+
+    #[test]
+    fn test_optimize() {
+        let positions = get_test_input();
+
+        assert_eq!(
+            optimize(&LinearCost, &positions),
+            Alignment {
+                position: 2,
+                fuel: 37
+            }
+        );
+        assert_eq!(
+            optimize(&TriangularCost, &positions),
+            Alignment {
+                position: 5,
+                fuel: 168
+            }
+        );
+        assert_eq!(
+            optimize(&QuadraticCost, &positions),
+            Alignment {
+                position: 5,
+                fuel: 291
+            }
+        );
+
+        // Moving is free up to 3 steps, then very expensive: not convex
+        let stepped = CustomCost {
+            cost: |dist| if dist <= 3 { 0 } else { 100 },
+            is_convex: false,
+        };
+        assert_eq!(
+            optimize(&stepped, &positions),
+            Alignment {
+                position: 1,
+                fuel: 300
+            }
+        );
+    }
+
+    #[test]
+    fn test_optimize_convex_matches_scan() {
+        let positions = vec![3, 9, 9, 12, 40, 41, 57, 100, -8, 0, 77];
+        let cubic = |dist: isize| dist * dist * dist;
+
+        assert_eq!(
+            optimize(
+                &CustomCost {
+                    cost: cubic,
+                    is_convex: true
+                },
+                &positions
+            ),
+            optimize(
+                &CustomCost {
+                    cost: cubic,
+                    is_convex: false
+                },
+                &positions
+            )
+        );
+    }
+
+    #[test]
+    fn test_optimize_decreasing_cost() {
+        // Convex, but cheapest at 5 steps: total fuel has more than one dip
+        let positions = vec![3, 6, 12, 25];
+        let dip = |dist: isize| (dist - 5) * (dist - 5);
+
+        assert_eq!(
+            optimize(
+                &CustomCost {
+                    cost: dip,
+                    is_convex: true
+                },
+                &positions
+            ),
+            optimize(
+                &CustomCost {
+                    cost: dip,
+                    is_convex: false
+                },
+                &positions
+            )
+        );
+    }
+
+    #[test]
+    fn test_fuel_report() {
+        let report = FuelReport::new(&LinearCost, &[16, 1, 2, 0, 4]);
+
+        assert_eq!(
+            report.alignment,
+            Alignment {
+                position: 2,
+                fuel: 19
+            }
+        );
+        assert_eq!(
+            report.crab_fuel,
+            vec![(16, 14), (1, 1), (2, 0), (0, 2), (4, 2)]
+        );
+        assert_eq!(report.cost_curve.len(), 17);
+        assert_eq!(
+            report.cost_curve.iter().map(|a| a.fuel).min(),
+            Some(report.alignment.fuel)
+        );
+
+        assert_eq!(
+            report.crab_fuel_csv(),
+            "crab,start_position,end_position,fuel\n\
+             0,16,2,14\n\
+             1,1,2,1\n\
+             2,2,2,0\n\
+             3,0,2,2\n\
+             4,4,2,2\n"
+        );
+        assert!(report.cost_curve_csv().starts_with(
+            "position,fuel,optimal\n\
+             0,23,false\n\
+             1,20,false\n\
+             2,19,true\n\
+             3,20,false\n"
+        ));
+    }
+}
@@ -1,5 +1,7 @@
+mod data;
 mod input;
 
+use data::*;
 use input::*;
 
 fn main() {
@@ -10,48 +12,13 @@ fn main() {
 
 // Determine the horizontal position that the crabs can align to using the least fuel possible. How
 // much fuel must they spend to align to that position?
-fn part_1(mut positions: Vec<isize>) -> isize {
-    positions.sort();
-    let min = positions[0];
-    let max = positions[positions.len() - 1];
-
-    // (cost, end_position)
-    let mut lowest_config = (isize::MAX, isize::MAX);
-
-    for end_position in min..=max {
-        let cost = positions.iter().map(|p| (p - end_position).abs()).sum();
-        if cost < lowest_config.0 {
-            lowest_config = (cost, end_position);
-        }
-    }
-
-    lowest_config.0
+fn part_1(positions: Vec<isize>) -> isize {
+    align_linear(&positions).fuel
 }
 
 // Same, but each change of 1 step in horizontal position costs 1 more unit of fuel than the last
-fn part_2(mut positions: Vec<isize>) -> isize {
-    positions.sort();
-    let min = positions[0];
-    let max = positions[positions.len() - 1];
-
-    // (cost, end_position)
-    let mut lowest_config = (isize::MAX, isize::MAX);
-
-    for end_position in min..=max {
-        let cost = positions
-            .iter()
-            .map(|pos| {
-                let dist = (pos - end_position).abs();
-                dist * (dist + 1) / 2
-            })
-            .sum();
-
-        if cost < lowest_config.0 {
-            lowest_config = (cost, end_position);
-        }
-    }
-
-    lowest_config.0
+fn part_2(positions: Vec<isize>) -> isize {
+    align_triangular(&positions).fuel
 }
 
 #[cfg(test)]
@@ -1,4 +1,5 @@
-use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::collections::BTreeSet;
+use std::ops::ControlFlow;
 use std::str::FromStr;
 
 #[derive(Eq, PartialEq, Debug)]
@@ -7,221 +8,464 @@ pub struct InputLine {
     pub outputs: Vec<SignalPattern>,
 }
 
+#[derive(Eq, PartialEq, Debug)]
+pub enum SolveError {
+    // No wire mapping turns every pattern into a symbol
+    Impossible,
+    // More than one wire mapping fits; two of them. From decode_with(), the two decode the outputs
+    // differently.
+    Ambiguous(Solution, Solution),
+    // The outputs decode to these digits, but they're too many for a usize
+    TooLarge(String),
+}
+
 impl InputLine {
-    // Yikes
+    // Parses a line whose patterns may use any of the encoding's segments
+    pub fn parse_with(s: &str, encoding: &DisplayEncoding) -> Result<Self, String> {
+        let (signals_str, outputs_str) = s
+            .split_once(" | ")
+            .ok_or_else(|| format!("missing ' | ' in {:?}", s))?;
+        let parse_patterns = |patterns_str: &str| {
+            patterns_str
+                .split(' ')
+                .map(|p| SignalPattern::parse_with(p, encoding.segment_count()))
+                .collect::<Result<_, _>>()
+        };
+
+        Ok(InputLine {
+            signals: parse_patterns(signals_str)?,
+            outputs: parse_patterns(outputs_str)?,
+        })
+    }
+
     pub fn solve(&self) -> usize {
-        let mut solves = BTreeMap::new();
+        self.decode().unwrap()
+    }
+
+    // Decodes the output value of a standard seven-segment digit display. More than 19 or so
+    // outputs can be too many digits for a usize.
+    pub fn decode(&self) -> Result<usize, SolveError> {
+        let digits = self.decode_with(&DisplayEncoding::seven_segment_digits())?;
+        digits.parse().map_err(|_| SolveError::TooLarge(digits))
+    }
+
+    // Decodes the outputs into the encoding's symbols. Succeeds even when the wire mapping isn't
+    // unique, as long as every possible mapping agrees on the outputs. Rather than trying every
+    // mapping, which can be a great many on a wide display, this finds one and then looks for
+    // another that reads some output as a different symbol.
+    pub fn decode_with(&self, encoding: &DisplayEncoding) -> Result<String, SolveError> {
+        let patterns = self.patterns();
+        let first = encoding
+            .first_solution(&patterns, None)
+            .ok_or(SolveError::Impossible)?;
+        let decoded: String = self
+            .outputs
+            .iter()
+            .map(|o| first.decode(encoding, o).unwrap())
+            .collect();
 
-        let mut seg_counts: HashMap<char, usize> = HashMap::new();
-        for sig in self.signals.iter() {
-            for seg in sig.segments.iter().filter(|&&c| c != '\u{0}') {
-                *seg_counts.entry(*seg).or_insert(0) += 1;
+        for (output, symbol) in self.outputs.iter().zip(decoded.chars()) {
+            if let Some(other) = encoding.first_solution(&patterns, Some((*output, symbol))) {
+                return Err(SolveError::Ambiguous(first, other));
             }
         }
 
-        let seven_chars = self
-            .signals
-            .iter()
-            .find(|s| s.segment_count == 3)
-            .unwrap()
-            .chars();
-        let one_chars = self
-            .signals
-            .iter()
-            .find(|s| s.segment_count == 2)
-            .unwrap()
-            .chars();
-        let four_chars = self
-            .signals
+        Ok(decoded)
+    }
+
+    // The wire mapping, if exactly one fits every pattern
+    #[allow(dead_code)]
+    pub fn solve_mapping(&self) -> Result<Solution, SolveError> {
+        let mut solutions = vec![];
+        DisplayEncoding::seven_segment_digits().search(&self.patterns(), None, &mut |solution| {
+            solutions.push(solution);
+            if solutions.len() < 2 {
+                ControlFlow::Continue(())
+            } else {
+                ControlFlow::Break(())
+            }
+        });
+
+        let mut solutions = solutions.into_iter();
+        match (solutions.next(), solutions.next()) {
+            (None, _) => Err(SolveError::Impossible),
+            (Some(solution), None) => Ok(solution),
+            (Some(first), Some(second)) => Err(SolveError::Ambiguous(first, second)),
+        }
+    }
+
+    // Every wire mapping under which all signal and output patterns are valid symbols. There can be
+    // a great many on a wide display with few patterns.
+    #[allow(dead_code)]
+    pub fn solutions_with(&self, encoding: &DisplayEncoding) -> Vec<Solution> {
+        encoding.solutions(&self.patterns())
+    }
+
+    fn patterns(&self) -> BTreeSet<SignalPattern> {
+        self.signals
             .iter()
-            .find(|s| s.segment_count == 4)
-            .unwrap()
-            .chars();
+            .chain(self.outputs.iter())
+            .copied()
+            .collect()
+    }
+}
 
-        // Solve 'a': the char in 7 that's not in 1
-        solves.insert('a', *seven_chars.difference(&one_chars).nth(0).unwrap());
+impl FromStr for InputLine {
+    type Err = String;
 
-        // Solve 'f': the char in 9 numbers
-        solves.insert(
-            'f',
-            *seg_counts
-                .iter()
-                .find(|(_seg, &count)| count == 9)
-                .unwrap()
-                .0,
-        );
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        InputLine::parse_with(s, &DisplayEncoding::seven_segment_digits())
+    }
+}
 
-        // Solve 'b': the char in 6 numbers
-        solves.insert(
-            'b',
-            *seg_counts
-                .iter()
-                .find(|(_seg, &count)| count == 6)
-                .unwrap()
-                .0,
-        );
+// The symbols a display can show, each as the segments it lights. Segments are named 'a', 'b', ...
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct DisplayEncoding {
+    segment_count: usize,
+    symbols: Vec<(SignalPattern, char)>,
+}
 
-        // Solve 'e': the char in 4 numbers
-        solves.insert(
-            'e',
-            *seg_counts
-                .iter()
-                .find(|(_seg, &count)| count == 4)
-                .unwrap()
-                .0,
-        );
+impl DisplayEncoding {
+    pub fn new(segment_count: usize, symbols: Vec<(SignalPattern, char)>) -> Result<Self, String> {
+        if segment_count > SignalPattern::MAX_SEGMENTS {
+            return Err(format!("too many segments: {}", segment_count));
+        }
 
-        // Solve 'c': the unsolved char in 7
-        solves.insert('c', {
-            let solved = solves.clone().into_values().collect();
-            *seven_chars.difference(&solved).nth(0).unwrap()
-        });
+        for (idx, (pattern, symbol)) in symbols.iter().enumerate() {
+            if pattern
+                .chars()
+                .any(|c| SignalPattern::index_of(c) >= segment_count)
+            {
+                return Err(format!("symbol {:?} uses an unknown segment", symbol));
+            }
+            if symbols[..idx].iter().any(|(other, _)| other == pattern) {
+                return Err(format!(
+                    "symbol {:?} has the same segments as another",
+                    symbol
+                ));
+            }
+        }
+
+        Ok(DisplayEncoding {
+            segment_count,
+            symbols,
+        })
+    }
 
-        // Solve 'd': the unsolved char in 4
-        solves.insert('d', {
-            let solved = solves.clone().into_values().collect();
-            *four_chars.difference(&solved).nth(0).unwrap()
+    pub fn seven_segment_digits() -> Self {
+        DisplayEncoding::new(
+            7,
+            vec![
+                (SignalPattern::from_segments("abcefg"), '0'),
+                (SignalPattern::from_segments("cf"), '1'),
+                (SignalPattern::from_segments("acdeg"), '2'),
+                (SignalPattern::from_segments("acdfg"), '3'),
+                (SignalPattern::from_segments("bcdf"), '4'),
+                (SignalPattern::from_segments("abdfg"), '5'),
+                (SignalPattern::from_segments("abdefg"), '6'),
+                (SignalPattern::from_segments("acf"), '7'),
+                (SignalPattern::from_segments("abcdefg"), '8'),
+                (SignalPattern::from_segments("abcdfg"), '9'),
+            ],
+        )
+        .unwrap()
+    }
+
+    // Digits plus A, b, C, d, E, F
+    #[allow(dead_code)]
+    pub fn seven_segment_hex() -> Self {
+        let mut symbols = DisplayEncoding::seven_segment_digits().symbols;
+        symbols.extend([
+            (SignalPattern::from_segments("abcdef"), 'A'),
+            (SignalPattern::from_segments("bdefg"), 'B'),
+            (SignalPattern::from_segments("abeg"), 'C'),
+            (SignalPattern::from_segments("cdefg"), 'D'),
+            (SignalPattern::from_segments("abdeg"), 'E'),
+            (SignalPattern::from_segments("abde"), 'F'),
+        ]);
+
+        DisplayEncoding::new(7, symbols).unwrap()
+    }
+
+    pub fn segment_count(&self) -> usize {
+        self.segment_count
+    }
+
+    // (segments, symbol) for every symbol
+    #[allow(dead_code)]
+    pub fn symbols(&self) -> impl Iterator<Item = (SignalPattern, char)> + '_ {
+        self.symbols.iter().copied()
+    }
+
+    pub fn symbol(&self, segments: SignalPattern) -> Option<char> {
+        self.symbols
+            .iter()
+            .find(|(pattern, _)| *pattern == segments)
+            .map(|(_, symbol)| *symbol)
+    }
+
+    // The symbol lighting this many segments, if it's the only one that does
+    pub fn symbol_by_segment_count(&self, segment_count: u32) -> Option<char> {
+        let mut matching = self
+            .symbols
+            .iter()
+            .filter(|(pattern, _)| pattern.segment_count() == segment_count);
+
+        match (matching.next(), matching.next()) {
+            (Some((_, symbol)), None) => Some(*symbol),
+            _ => None,
+        }
+    }
+
+    // Every wire mapping under which all patterns are valid symbols
+    #[allow(dead_code)]
+    pub fn solutions(&self, patterns: &BTreeSet<SignalPattern>) -> Vec<Solution> {
+        let mut solutions = vec![];
+        self.search(patterns, None, &mut |solution| {
+            solutions.push(solution);
+            ControlFlow::Continue(())
         });
+        solutions
+    }
 
-        // Solve 'g': the final unsolved char
-        solves.insert('g', {
-            let solved = solves.clone().into_values().collect();
-            *('a'..='g')
-                .collect::<HashSet<char>>()
-                .difference(&solved)
-                .nth(0)
-                .unwrap()
+    // A wire mapping under which all patterns are valid symbols, and the excluded pattern (if any)
+    // isn't the excluded symbol
+    fn first_solution(
+        &self,
+        patterns: &BTreeSet<SignalPattern>,
+        excluded: Option<(SignalPattern, char)>,
+    ) -> Option<Solution> {
+        let mut first = None;
+        self.search(patterns, excluded, &mut |solution| {
+            first = Some(solution);
+            ControlFlow::Break(())
         });
+        first
+    }
+
+    // Passes each wire mapping that fits to `found`, until it says to stop. Segments are assigned
+    // wires one at a time, backtracking as soon as some pattern can no longer be a symbol.
+    fn search(
+        &self,
+        patterns: &BTreeSet<SignalPattern>,
+        excluded: Option<(SignalPattern, char)>,
+        found: &mut dyn FnMut(Solution) -> ControlFlow<()>,
+    ) {
+        let mut wires = vec![None; self.segment_count];
+        let _ = self.assign_segment(0, &mut wires, patterns, excluded, found);
+    }
+
+    // wires[segment] = index of the wire driving that segment
+    fn assign_segment(
+        &self,
+        segment: usize,
+        wires: &mut Vec<Option<usize>>,
+        patterns: &BTreeSet<SignalPattern>,
+        excluded: Option<(SignalPattern, char)>,
+        found: &mut dyn FnMut(Solution) -> ControlFlow<()>,
+    ) -> ControlFlow<()> {
+        if segment == self.segment_count {
+            return found(Solution::from(&wires[..]));
+        }
+
+        for wire in 0..self.segment_count {
+            if wires.contains(&Some(wire)) {
+                continue;
+            }
 
-        // When given "cf", returns the solved mapping, like {'a', 'b'}
-        let solves_for = |chars: &str| -> BTreeSet<char> {
-            solves
+            wires[segment] = Some(wire);
+            if patterns
                 .iter()
-                .filter(|(og_char, _)| chars.contains(**og_char))
-                .map(|(_, solved_char)| *solved_char)
-                .collect()
-        };
+                .all(|p| self.could_be_symbol(wires, p, excluded))
+            {
+                self.assign_segment(segment + 1, wires, patterns, excluded, found)?;
+            }
+            wires[segment] = None;
+        }
 
-        // Construct a map of (set of solved chars) => (digit)
-        // BTreeMap<{'b', 'c', 'a', ...}, '7'}>
-        let mut solved_numbers: BTreeMap<BTreeSet<char>, char> = BTreeMap::new();
-        solved_numbers.insert(solves_for("abcefg"), '0');
-        solved_numbers.insert(solves_for("cf"), '1');
-        solved_numbers.insert(solves_for("acdeg"), '2');
-        solved_numbers.insert(solves_for("acdfg"), '3');
-        solved_numbers.insert(solves_for("bcdf"), '4');
-        solved_numbers.insert(solves_for("abdfg"), '5');
-        solved_numbers.insert(solves_for("abdefg"), '6');
-        solved_numbers.insert(solves_for("acf"), '7');
-        solved_numbers.insert(solves_for("abcdefg"), '8');
-        solved_numbers.insert(solves_for("abcdfg"), '9');
-
-        self.outputs
-            .iter()
-            .map(|o| solved_numbers.get(&o.chars()).unwrap())
-            .collect::<String>()
-            .parse()
-            .unwrap()
+        ControlFlow::Continue(())
+    }
+
+    // Whether some symbol (other than an excluded one) agrees with the pattern on every segment
+    // assigned so far
+    fn could_be_symbol(
+        &self,
+        wires: &[Option<usize>],
+        pattern: &SignalPattern,
+        excluded: Option<(SignalPattern, char)>,
+    ) -> bool {
+        self.symbols.iter().any(|(symbol, c)| {
+            excluded != Some((*pattern, *c))
+                && symbol.segment_count() == pattern.segment_count()
+                && wires.iter().enumerate().all(|(segment, wire)| match wire {
+                    Some(wire) => symbol.has(segment) == pattern.has(*wire),
+                    None => true,
+                })
+        })
     }
 }
 
-impl FromStr for InputLine {
+// One symbol per line: the symbol, a space, then the segments it lights. Blank lines are skipped.
+// The segment count is taken from the highest segment used.
+impl FromStr for DisplayEncoding {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (signals_str, outputs_str) = s.split_once(" | ").unwrap();
+        let symbols = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                let (symbol, segments) = line
+                    .split_once(' ')
+                    .ok_or_else(|| format!("expected '<symbol> <segments>', got {:?}", line))?;
+                let mut symbol_chars = symbol.chars();
+                match (symbol_chars.next(), symbol_chars.next()) {
+                    (Some(symbol), None) => Ok((
+                        SignalPattern::parse_with(segments, SignalPattern::MAX_SEGMENTS)?,
+                        symbol,
+                    )),
+                    _ => Err(format!("symbol must be one char, got {:?}", symbol)),
+                }
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        let segment_count = symbols
+            .iter()
+            .flat_map(|(pattern, _)| pattern.chars())
+            .map(|c| SignalPattern::index_of(c) + 1)
+            .max()
+            .unwrap_or(0);
 
-        Ok(InputLine {
-            signals: signals_str.split(' ').map(|s| s.parse().unwrap()).collect(),
-            outputs: outputs_str.split(' ').map(|s| s.parse().unwrap()).collect(),
-        })
+        DisplayEncoding::new(segment_count, symbols)
     }
 }
 
-#[derive(Default, Debug)]
-pub struct Solution {
-    pub a: Option<char>,
-    pub b: Option<char>,
-    pub c: Option<char>,
-    pub d: Option<char>,
-    pub e: Option<char>,
-    pub f: Option<char>,
-    pub g: Option<char>,
+// Which scrambled wire drives each real segment; Solution[segment] = wire
+#[derive(Eq, PartialEq, Clone, Default, Debug)]
+pub struct Solution(pub Vec<Option<char>>);
+
+impl Solution {
+    // The symbol shown when the given wires are lit, if any
+    pub fn decode(&self, encoding: &DisplayEncoding, pattern: &SignalPattern) -> Option<char> {
+        let segments = self
+            .0
+            .iter()
+            .enumerate()
+            .filter(|(_, wire)| wire.is_some_and(|w| pattern.has(SignalPattern::index_of(w))))
+            .fold(SignalPattern::EMPTY, |segments, (segment, _)| {
+                segments.union(SignalPattern::single(segment))
+            });
+
+        if segments.segment_count() != pattern.segment_count() {
+            return None;
+        }
+
+        encoding.symbol(segments)
+    }
 }
 
-#[derive(Eq, PartialEq, Debug)]
-pub struct SignalPattern {
-    segments: [char; 7],
-    segment_count: usize,
+impl From<&[Option<usize>]> for Solution {
+    fn from(wires: &[Option<usize>]) -> Self {
+        Solution(
+            wires
+                .iter()
+                .map(|wire| wire.map(SignalPattern::char_of))
+                .collect(),
+        )
+    }
 }
 
+// Bit n is set when wire/segment n ('a' + n) is lit
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Default, Debug)]
+pub struct SignalPattern(u32);
+
 impl SignalPattern {
-    pub fn ez_num(&self) -> Option<usize> {
-        match self.segment_count {
-            2 => Some(1),
-            4 => Some(4),
-            3 => Some(7),
-            7 => Some(8),
-            _ => None,
+    pub const EMPTY: SignalPattern = SignalPattern(0);
+
+    // Segments 'a'..='z'
+    pub const MAX_SEGMENTS: usize = 26;
+
+    // For building tables; assumes every char is a valid segment
+    fn from_segments(segments: &str) -> SignalPattern {
+        segments.chars().fold(SignalPattern::EMPTY, |pattern, c| {
+            pattern.union(SignalPattern::single(SignalPattern::index_of(c)))
+        })
+    }
+
+    // Parses a pattern of a display with the given number of segments
+    pub fn parse_with(s: &str, segment_count: usize) -> Result<Self, String> {
+        let mut pattern = SignalPattern::EMPTY;
+
+        for char in s.chars() {
+            if !char.is_ascii_lowercase() || SignalPattern::index_of(char) >= segment_count {
+                return Err(format!("invalid segment {:?} in {:?}", char, s));
+            }
+
+            let segment = SignalPattern::single(SignalPattern::index_of(char));
+            if pattern.intersection(segment) != SignalPattern::EMPTY {
+                return Err(format!("duplicate segment {:?} in {:?}", char, s));
+            }
+            pattern = pattern.union(segment);
         }
+
+        Ok(pattern)
     }
 
-    pub fn chars(&self) -> BTreeSet<char> {
-        self.segments
-            .clone()
-            .into_iter()
-            .take(self.segment_count)
-            .collect()
+    pub fn single(idx: usize) -> SignalPattern {
+        SignalPattern(1 << idx)
+    }
+
+    pub fn index_of(char: char) -> usize {
+        (char as u8 - b'a') as usize
+    }
+
+    pub fn char_of(idx: usize) -> char {
+        (b'a' + idx as u8) as char
+    }
+
+    pub fn segment_count(&self) -> u32 {
+        self.0.count_ones()
+    }
+
+    pub fn has(&self, idx: usize) -> bool {
+        self.0 & (1 << idx) != 0
+    }
+
+    pub fn union(self, other: SignalPattern) -> SignalPattern {
+        SignalPattern(self.0 | other.0)
+    }
+
+    pub fn intersection(self, other: SignalPattern) -> SignalPattern {
+        SignalPattern(self.0 & other.0)
+    }
+
+    #[allow(dead_code)]
+    pub fn difference(self, other: SignalPattern) -> SignalPattern {
+        SignalPattern(self.0 & !other.0)
+    }
+
+    pub fn chars(&self) -> impl Iterator<Item = char> + '_ {
+        (0..SignalPattern::MAX_SEGMENTS)
+            .filter(|&idx| self.has(idx))
+            .map(SignalPattern::char_of)
     }
 }
 
+// Parses a pattern of a standard seven-segment display
 impl FromStr for SignalPattern {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut segments: [char; 7] = Default::default();
-        let mut segment_count = 0;
-
-        for (idx, char) in s.chars().enumerate() {
-            segments[idx] = char;
-            segment_count += 1;
-        }
-
-        Ok(SignalPattern {
-            segments,
-            segment_count,
-        })
+        SignalPattern::parse_with(s, 7)
     }
 }
 
 #[cfg(test)]
 macro_rules! signal_pattern {
     ($($char_:expr),*) => {
-        {
-            #[allow(unused_mut)]
-            let mut segments: [char; 7] = Default::default();
-            #[allow(unused_mut)]
-            let mut segment_count = 0;
-            signal_pattern!(@recurse segments, segment_count, 0usize, $($char_),*);
-            SignalPattern { segments, segment_count }
-        }
+        SignalPattern(0 $(| 1 << SignalPattern::index_of($char_))*)
     };
-
-    // Recursively set chars and increment the count. The separate $head_char and $tail_char,*
-    // captures cause each pass through this rule to "shift" off the 0th char.
-    (@recurse $seg:ident, $seg_count:ident, $idx:expr, $head_char:expr, $($tail_char:expr),*) => {
-        $seg[$idx] = $head_char;
-        $seg_count += 1;
-        signal_pattern!(@recurse $seg, $seg_count, $idx + 1usize, $($tail_char),*);
-    };
-
-    (@recurse $seg:ident, $seg_count:ident, $idx:expr, $head_char:expr) => {
-        $seg[$idx] = $head_char;
-        $seg_count += 1;
-    };
-
-    // This gets called at the last recursion step, when there's no more tail_chars
-    (@recurse $seg:ident, $seg_count:ident, $idx:expr, $($tail_char:expr),*) => {};
 }
 
 #[cfg(test)]
@@ -237,37 +481,36 @@ mod tests {
 
     #[test]
     fn test_signal_pattern_macro() {
+        assert_eq!(signal_pattern![], SignalPattern(0));
+        assert_eq!(signal_pattern!['a'], SignalPattern(0b0000001));
+        assert_eq!(signal_pattern!['b', 'e'], SignalPattern(0b0010010));
         assert_eq!(
-            signal_pattern![],
-            SignalPattern {
-                segments: Default::default(),
-                segment_count: 0,
-            }
-        );
-
-        assert_eq!(
-            signal_pattern!['a'],
-            SignalPattern {
-                segments: ['a', '\u{0}', '\u{0}', '\u{0}', '\u{0}', '\u{0}', '\u{0}'],
-                segment_count: 1,
-            }
+            signal_pattern!['a', 'b', 'c', 'd', 'e', 'f', 'g'],
+            SignalPattern(0b1111111)
         );
+    }
 
-        assert_eq!(
-            signal_pattern!['b', 'e'],
-            SignalPattern {
-                segments: ['b', 'e', '\u{0}', '\u{0}', '\u{0}', '\u{0}', '\u{0}'],
-                segment_count: 2,
-            }
-        );
+    #[test]
+    fn test_signal_pattern_from_str() {
+        assert_eq!("eb".parse(), Ok(signal_pattern!['b', 'e']));
+        assert_eq!("".parse(), Ok(SignalPattern::EMPTY));
+        assert!("abch".parse::<SignalPattern>().is_err());
+        assert!("abca".parse::<SignalPattern>().is_err());
+        assert!("abcdefgh".parse::<SignalPattern>().is_err());
+        assert!("ab | cd ex".parse::<InputLine>().is_err());
+    }
 
-        assert_eq!(
-            signal_pattern!['a', 'b', 'c', 'd', 'e', 'f', 'g'],
-            SignalPattern {
-                segments: ['a', 'b', 'c', 'd', 'e', 'f', 'g'],
-                segment_count: 7,
-            }
-        );
+    #[test]
+    fn test_signal_pattern_set_operations() {
+        let seven = signal_pattern!['a', 'c', 'f'];
+        let one = signal_pattern!['c', 'f'];
+        let four = signal_pattern!['b', 'c', 'd', 'f'];
+
+        assert_eq!(seven.difference(one), signal_pattern!['a']);
+        assert_eq!(seven.union(four), signal_pattern!['a', 'b', 'c', 'd', 'f']);
+        assert_eq!(seven.intersection(four), one);
+        assert_eq!(four.segment_count(), 4);
+        assert_eq!(four.chars().collect::<String>(), "bcdf");
     }
 
     #[test]
@@ -279,4 +522,170 @@ mod tests {
 
         assert_eq!(input_line.solve(), 5353);
     }
+
+    #[test]
+    fn test_input_line_solve_mapping() {
+        let input_line: InputLine =
+            "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf"
+                .parse()
+                .unwrap();
+
+        assert_eq!(
+            input_line.solve_mapping(),
+            Ok(Solution("deafgbc".chars().map(Some).collect()))
+        );
+    }
+
+    #[test]
+    fn test_input_line_missing_digits() {
+        // No 0, 6 or 9, but 2, 3 and 5 are enough to tell the remaining wires apart
+        let input_line: InputLine =
+            "acedgfb cdfbe gcdfa fbcad dab eafb ab | cdfeb fcadb cdfeb cdbaf"
+                .parse()
+                .unwrap();
+        assert!(input_line.solve_mapping().is_ok());
+        assert_eq!(input_line.decode(), Ok(5353));
+
+        // Only a 1 and an 8: many mappings fit, but they all read the outputs the same way
+        let input_line: InputLine = "ab acedgfb | ab acedgfb ba ab".parse().unwrap();
+        assert!(matches!(
+            input_line.solve_mapping(),
+            Err(SolveError::Ambiguous(..))
+        ));
+        assert_eq!(input_line.decode(), Ok(1811));
+
+        // Only an 8: the five-segment output could be a 2, 3 or 5
+        let input_line: InputLine = "acedgfb | cdfbe".parse().unwrap();
+        assert!(matches!(
+            input_line.decode(),
+            Err(SolveError::Ambiguous(..))
+        ));
+    }
+
+    #[test]
+    fn test_input_line_too_large() {
+        let ones = |count| format!("ab | {}", vec!["ab"; count].join(" "));
+
+        let input_line: InputLine = ones(19).parse().unwrap();
+        assert_eq!(input_line.decode(), Ok(1_111_111_111_111_111_111));
+
+        let input_line: InputLine = ones(21).parse().unwrap();
+        assert_eq!(
+            input_line.decode(),
+            Err(SolveError::TooLarge("1".repeat(21)))
+        );
+    }
+
+    #[test]
+    fn test_input_line_impossible() {
+        // Two different 1s
+        let input_line: InputLine = "ab cd | ab".parse().unwrap();
+        assert_eq!(input_line.solve_mapping(), Err(SolveError::Impossible));
+        assert_eq!(input_line.decode(), Err(SolveError::Impossible));
+    }
+
+    // Scrambles each symbol's segments with the given wiring (wiring[segment] = wire)
+    fn scramble(encoding: &DisplayEncoding, wiring: &str, symbols: &str) -> String {
+        let wiring: Vec<char> = wiring.chars().collect();
+        symbols
+            .chars()
+            .map(|symbol| {
+                let (pattern, _) = encoding.symbols.iter().find(|(_, s)| *s == symbol).unwrap();
+                pattern
+                    .chars()
+                    .map(|c| wiring[SignalPattern::index_of(c)])
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn test_symbol_by_segment_count() {
+        let digits = DisplayEncoding::seven_segment_digits();
+        assert_eq!(digits.symbol_by_segment_count(2), Some('1'));
+        assert_eq!(digits.symbol_by_segment_count(4), Some('4'));
+        assert_eq!(digits.symbol_by_segment_count(5), None);
+
+        // 4 and C both light four segments
+        let hex = DisplayEncoding::seven_segment_hex();
+        assert_eq!(hex.symbol_by_segment_count(4), None);
+    }
+
+    #[test]
+    fn test_decode_hex() {
+        let hex = DisplayEncoding::seven_segment_hex();
+        let line = format!(
+            "{} | {}",
+            scramble(&hex, "gfedcba", "0123456789ABCDEF"),
+            scramble(&hex, "gfedcba", "BEEF")
+        );
+
+        let input_line = InputLine::parse_with(&line, &hex).unwrap();
+        assert_eq!(input_line.decode_with(&hex), Ok("BEEF".to_string()));
+        assert_eq!(input_line.solutions_with(&hex).len(), 1);
+    }
+
+    #[test]
+    fn test_decode_custom_encoding() {
+        // A nine-segment display: a seven-segment display with two diagonals (h, i) added
+        let encoding: DisplayEncoding = "
+            0 abcefg
+            1 cf
+            7 acf
+            8 abcdefg
+            X hi
+            N bcefh
+            Z acegi
+        "
+        .parse()
+        .unwrap();
+        assert_eq!(encoding.segment_count(), 9);
+
+        let line = format!(
+            "{} | {}",
+            scramble(&encoding, "ihgfedcba", "0178XNZ"),
+            scramble(&encoding, "ihgfedcba", "ZX10")
+        );
+        let input_line = InputLine::parse_with(&line, &encoding).unwrap();
+        assert_eq!(input_line.decode_with(&encoding), Ok("ZX10".to_string()));
+
+        // Segment j isn't part of this display
+        assert!(InputLine::parse_with("ab | aj", &encoding).is_err());
+    }
+
+    #[test]
+    fn test_decode_wide_display_sparse_entry() {
+        // Twelve segments, and a line that only shows the symbol lighting all of them: any of the
+        // 12! wirings fits, but they all read it the same way
+        let encoding: DisplayEncoding = "
+            W abcdefghijkl
+            I ab
+            - cd
+        "
+        .parse()
+        .unwrap();
+
+        let input_line = InputLine::parse_with("abcdefghijkl | abcdefghijkl", &encoding).unwrap();
+        assert_eq!(input_line.decode_with(&encoding), Ok("W".to_string()));
+
+        // A two-segment output could be I or -
+        let input_line = InputLine::parse_with("abcdefghijkl | kl", &encoding).unwrap();
+        let Err(SolveError::Ambiguous(first, second)) = input_line.decode_with(&encoding) else {
+            panic!("expected an ambiguous decoding");
+        };
+        let output = input_line.outputs[0];
+        assert_ne!(
+            first.decode(&encoding, &output),
+            second.decode(&encoding, &output)
+        );
+    }
+
+    #[test]
+    fn test_display_encoding_validation() {
+        assert!("0 abc\n1 cba".parse::<DisplayEncoding>().is_err());
+        assert!("10 abc".parse::<DisplayEncoding>().is_err());
+        assert!("0 ab1".parse::<DisplayEncoding>().is_err());
+        assert!(DisplayEncoding::new(2, vec![(signal_pattern!['c'], '0')]).is_err());
+    }
 }
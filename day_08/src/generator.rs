@@ -0,0 +1,121 @@
+// Random scrambled-display entries with a known wiring, for testing the decoder
+use crate::data::*;
+
+// xorshift64*; plenty for shuffling, and seeded so failures can be reproduced
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        // The state must never be 0
+        Rng(seed ^ 0x9E37_79B9_7F4A_7C15 | 1)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    // Uniform-ish in 0..n
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    // Fisher-Yates
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for idx in (1..items.len()).rev() {
+            items.swap(idx, self.below(idx + 1));
+        }
+    }
+}
+
+pub struct GeneratedEntry {
+    // Formatted like a puzzle input line
+    pub line: String,
+    // The wiring used to scramble the line
+    pub wiring: Solution,
+    // The symbols the outputs really show
+    pub outputs: String,
+}
+
+// Scrambles every symbol of the encoding (in random order) as the signals, plus `output_count`
+// random symbols as the outputs, using a random wiring
+pub fn generate_entry(
+    rng: &mut Rng,
+    encoding: &DisplayEncoding,
+    output_count: usize,
+) -> GeneratedEntry {
+    let mut wires: Vec<usize> = (0..encoding.segment_count()).collect();
+    rng.shuffle(&mut wires);
+
+    let symbols: Vec<char> = encoding.symbols().map(|(_, symbol)| symbol).collect();
+    let mut signals = symbols.clone();
+    rng.shuffle(&mut signals);
+    let outputs: String = (0..output_count)
+        .map(|_| symbols[rng.below(symbols.len())])
+        .collect();
+
+    let mut scramble = |symbol: char| -> String {
+        let (pattern, _) = encoding.symbols().find(|&(_, s)| s == symbol).unwrap();
+        let mut scrambled: Vec<char> = pattern
+            .chars()
+            .map(|c| SignalPattern::char_of(wires[SignalPattern::index_of(c)]))
+            .collect();
+        rng.shuffle(&mut scrambled);
+        scrambled.into_iter().collect()
+    };
+    let signals_str: Vec<String> = signals.into_iter().map(&mut scramble).collect();
+    let outputs_str: Vec<String> = outputs.chars().map(&mut scramble).collect();
+
+    GeneratedEntry {
+        line: format!("{} | {}", signals_str.join(" "), outputs_str.join(" ")),
+        wiring: Solution::from(&wires.into_iter().map(Some).collect::<Vec<_>>()[..]),
+        outputs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate_entry() {
+        let encoding = DisplayEncoding::seven_segment_digits();
+        let entry = generate_entry(&mut Rng::new(8), &encoding, 4);
+
+        let (signals, outputs) = entry.line.split_once(" | ").unwrap();
+        assert_eq!(signals.split(' ').count(), 10);
+        assert_eq!(outputs.split(' ').count(), 4);
+        assert_eq!(entry.outputs.len(), 4);
+        assert!(entry.line.parse::<InputLine>().is_ok());
+    }
+
+    #[test]
+    fn test_decode_generated_digits() {
+        let encoding = DisplayEncoding::seven_segment_digits();
+        let mut rng = Rng::new(2021);
+
+        for _ in 0..2000 {
+            let entry = generate_entry(&mut rng, &encoding, 4);
+            let input_line: InputLine = entry.line.parse().unwrap();
+
+            assert_eq!(input_line.decode(), Ok(entry.outputs.parse().unwrap()));
+            assert_eq!(input_line.solve_mapping(), Ok(entry.wiring));
+        }
+    }
+
+    #[test]
+    fn test_decode_generated_hex() {
+        let encoding = DisplayEncoding::seven_segment_hex();
+        let mut rng = Rng::new(16);
+
+        for _ in 0..500 {
+            let entry = generate_entry(&mut rng, &encoding, 6);
+            let input_line = InputLine::parse_with(&entry.line, &encoding).unwrap();
+
+            assert_eq!(input_line.decode_with(&encoding), Ok(entry.outputs));
+            assert_eq!(input_line.solutions_with(&encoding), vec![entry.wiring]);
+        }
+    }
+}
@@ -2,6 +2,8 @@
 // trace_macros!(true);
 
 mod data;
+#[cfg(test)]
+mod generator;
 mod input;
 
 use data::*;
@@ -18,11 +20,15 @@ fn main() {
 // easy digits that can be decoded by looking at the number of segments (1, 4, 7, 8). In the output
 // values, how many times do these digits appear?
 fn part_1(input_lines: Vec<InputLine>) -> usize {
+    let encoding = DisplayEncoding::seven_segment_digits();
     let mut count = 0;
 
     for input_line in input_lines.iter() {
         for signal_pattern in input_line.outputs.iter() {
-            if signal_pattern.ez_num().is_some() {
+            if encoding
+                .symbol_by_segment_count(signal_pattern.segment_count())
+                .is_some()
+            {
                 count += 1;
             }
         }
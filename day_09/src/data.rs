@@ -1,7 +1,8 @@
-use std::collections::{HashMap, HashSet};
+use crate::flow::{Connectivity, FlowModel};
+use std::collections::HashMap;
 
-#[derive(Eq, PartialEq, Hash, Debug)]
-pub struct Coord(isize, isize);
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone, Debug)]
+pub struct Coord(pub isize, pub isize);
 
 #[derive(Eq, PartialEq, Debug)]
 pub struct HeightMap {
@@ -34,36 +35,87 @@ impl HeightMap {
         })
     }
 
-    pub fn basin_size(&self, coord: &Coord) -> usize {
-        let mut counted = HashSet::new();
-        let mut current = vec![coord];
+    // (width, height)
+    pub fn dimensions(&self) -> (isize, isize) {
+        self.points.keys().fold((0, 0), |(w, h), coord| {
+            (w.max(coord.0 + 1), h.max(coord.1 + 1))
+        })
+    }
+
+    // Every coord, top row first and left to right within a row
+    pub fn coords_in_order(&self) -> Vec<Coord> {
+        let mut coords: Vec<Coord> = self.points.keys().copied().collect();
+        coords.sort_by_key(|coord| (coord.1, coord.0));
+        coords
+    }
 
-        while !current.is_empty() {
-            let mut next = vec![];
+    // Basins as the puzzle defines them: separated by height 9, no diagonals
+    pub fn basins(&self) -> BasinMap {
+        self.basins_with(&FlowModel::default())
+    }
 
-            for coord in current.iter() {
-                if !counted.contains(coord) {
-                    counted.insert(coord.clone());
+    // Labels every location below the ridge height with the basin it belongs to, by unioning each
+    // location with its neighbors. Basin IDs are assigned in reading order of each basin's first
+    // location.
+    pub fn basins_with(&self, model: &FlowModel) -> BasinMap {
+        let coords: Vec<Coord> = self
+            .coords_in_order()
+            .into_iter()
+            .filter(|coord| !model.is_ridge(self.points[coord]))
+            .collect();
+        let indexes: HashMap<Coord, usize> = coords
+            .iter()
+            .enumerate()
+            .map(|(idx, &coord)| (coord, idx))
+            .collect();
+
+        let mut union_find = UnionFind::new(coords.len());
+        for (idx, coord) in coords.iter().enumerate() {
+            // Neighbors later in reading order are enough to visit every adjacent pair once
+            for neighbor in model.connectivity.later_neighbors(coord) {
+                if let Some(&neighbor_idx) = indexes.get(&neighbor) {
+                    union_find.union(idx, neighbor_idx);
                 }
-
-                let height = self.points.get(coord).unwrap();
-
-                self.adjacent_points(coord)
-                    .into_iter()
-                    .filter(|point| {
-                        if let Some((_adj_coord, &adj_height)) = point {
-                            adj_height > *height && adj_height < 9
-                        } else {
-                            false
-                        }
-                    })
-                    .for_each(|point| next.push(point.unwrap().0))
             }
+        }
 
-            current = next;
+        let mut labels = HashMap::new();
+        let mut basins: Vec<Basin> = vec![];
+        let mut root_ids = HashMap::new();
+
+        for (idx, &coord) in coords.iter().enumerate() {
+            let root = union_find.find(idx);
+            let id = *root_ids.entry(root).or_insert_with(|| {
+                basins.push(Basin {
+                    id: basins.len(),
+                    low_point: coord,
+                    members: vec![],
+                });
+                basins.len() - 1
+            });
+
+            let basin = &mut basins[id];
+            basin.members.push(coord);
+            if self.points[&coord] < self.points[&basin.low_point] {
+                basin.low_point = coord;
+            }
+            labels.insert(coord, id);
         }
 
-        counted.len()
+        BasinMap { labels, basins }
+    }
+
+    // (coord, height) of every neighbor on the map
+    #[allow(dead_code)]
+    pub fn neighbors(&self, coord: &Coord, connectivity: Connectivity) -> Vec<(Coord, u32)> {
+        connectivity
+            .offsets()
+            .iter()
+            .filter_map(|(dx, dy)| {
+                let neighbor = Coord(coord.0 + dx, coord.1 + dy);
+                self.points.get(&neighbor).map(|&height| (neighbor, height))
+            })
+            .collect()
     }
 
     pub fn adjacent_points(&self, coord: &Coord) -> [Option<(&Coord, &u32)>; 4] {
@@ -76,6 +128,64 @@ impl HeightMap {
     }
 }
 
+#[derive(Eq, PartialEq, Debug)]
+pub struct Basin {
+    pub id: usize,
+    // The lowest location in the basin (the first in reading order on ties)
+    pub low_point: Coord,
+    // In reading order
+    pub members: Vec<Coord>,
+}
+
+impl Basin {
+    pub fn size(&self) -> usize {
+        self.members.len()
+    }
+}
+
+#[derive(Eq, PartialEq, Debug)]
+pub struct BasinMap {
+    // Basin ID of every location that's in a basin
+    pub labels: HashMap<Coord, usize>,
+    // Indexed by basin ID
+    pub basins: Vec<Basin>,
+}
+
+// Disjoint sets over 0..n, with path halving and union by size
+struct UnionFind {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+}
+
+impl UnionFind {
+    fn new(n: usize) -> UnionFind {
+        UnionFind {
+            parents: (0..n).collect(),
+            sizes: vec![1; n],
+        }
+    }
+
+    fn find(&mut self, mut idx: usize) -> usize {
+        while self.parents[idx] != idx {
+            self.parents[idx] = self.parents[self.parents[idx]];
+            idx = self.parents[idx];
+        }
+        idx
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (mut a, mut b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        if self.sizes[a] < self.sizes[b] {
+            std::mem::swap(&mut a, &mut b);
+        }
+        self.parents[b] = a;
+        self.sizes[a] += self.sizes[b];
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -90,4 +200,53 @@ mod tests {
             Some(&8)
         );
     }
+
+    #[test]
+    fn test_basins() {
+        let height_map = HeightMap::new(get_test_input::<String>());
+        let basin_map = height_map.basins();
+
+        assert_eq!(
+            basin_map
+                .basins
+                .iter()
+                .map(|basin| (basin.id, basin.low_point, basin.size()))
+                .collect::<Vec<_>>(),
+            vec![
+                (0, Coord(1, 0), 3),
+                (1, Coord(9, 0), 9),
+                (2, Coord(2, 2), 14),
+                (3, Coord(6, 4), 9),
+            ]
+        );
+        assert_eq!(
+            basin_map.basins[0].members,
+            vec![Coord(0, 0), Coord(1, 0), Coord(0, 1)]
+        );
+
+        assert_eq!(basin_map.labels.get(&Coord(0, 0)), Some(&0));
+        assert_eq!(basin_map.labels.get(&Coord(2, 0)), None);
+        assert_eq!(basin_map.labels.get(&Coord(4, 3)), Some(&2));
+        assert_eq!(basin_map.labels.len(), 35);
+    }
+
+    #[test]
+    fn test_basins_with() {
+        let height_map = HeightMap::new(get_test_input::<String>());
+
+        // Diagonals slip through the ridges, e.g. (1, 0) to (2, 1), joining everything up
+        let diagonal = height_map.basins_with(&FlowModel {
+            ridge_height: 9,
+            connectivity: Connectivity::Eight,
+        });
+        assert_eq!(diagonal.basins.len(), 1);
+        assert_eq!(diagonal.basins[0].size(), 35);
+
+        // With a lower ridge, heights 8 split basins apart too
+        let low_ridge = height_map.basins_with(&FlowModel {
+            ridge_height: 8,
+            connectivity: Connectivity::Four,
+        });
+        assert_eq!(low_ridge.labels.len(), 35 - 10);
+    }
 }
@@ -0,0 +1,299 @@
+use crate::data::*;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Connectivity {
+    // Up, down, left and right
+    Four,
+    // Diagonals too
+    #[allow(dead_code)]
+    Eight,
+}
+
+impl Connectivity {
+    pub fn offsets(&self) -> &'static [(isize, isize)] {
+        match self {
+            Connectivity::Four => &[(-1, 0), (1, 0), (0, -1), (0, 1)],
+            Connectivity::Eight => &[
+                (-1, -1),
+                (0, -1),
+                (1, -1),
+                (-1, 0),
+                (1, 0),
+                (-1, 1),
+                (0, 1),
+                (1, 1),
+            ],
+        }
+    }
+
+    // Neighbors after this coord in reading order
+    pub fn later_neighbors(&self, coord: &Coord) -> Vec<Coord> {
+        self.offsets()
+            .iter()
+            .filter(|&&(dx, dy)| (dy, dx) > (0, 0))
+            .map(|(dx, dy)| Coord(coord.0 + dx, coord.1 + dy))
+            .collect()
+    }
+}
+
+// Rules for how water moves over a height map
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct FlowModel {
+    // Locations at least this high are ridges: water never sits on them
+    pub ridge_height: u32,
+    pub connectivity: Connectivity,
+}
+
+impl Default for FlowModel {
+    // The puzzle's rules
+    fn default() -> Self {
+        FlowModel {
+            ridge_height: 9,
+            connectivity: Connectivity::Four,
+        }
+    }
+}
+
+// Where water at a location goes next
+#[allow(dead_code)]
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum Step {
+    Ridge,
+    // No neighbor is as low; water stays here
+    Sink,
+    // No neighbor is lower, but some are just as low
+    Plateau(Vec<Coord>),
+    // The single lowest lower neighbor
+    Downhill(Coord),
+    // Several lower neighbors share the lowest height
+    Tie(Vec<Coord>),
+}
+
+// Where water starting at a location ends up
+#[allow(dead_code)]
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Downstream {
+    Ridge,
+    Sink(Coord),
+    // Descent stopped on a flat area at this location
+    Plateau(Coord),
+    // Descent split at this location
+    Tie(Coord),
+}
+
+impl FlowModel {
+    pub fn is_ridge(&self, height: u32) -> bool {
+        height >= self.ridge_height
+    }
+
+    // Steepest descent: move to the lowest lower neighbor
+    #[allow(dead_code)]
+    pub fn step(&self, height_map: &HeightMap, coord: &Coord) -> Step {
+        let height = height_map.points[coord];
+        if self.is_ridge(height) {
+            return Step::Ridge;
+        }
+
+        let neighbors = height_map.neighbors(coord, self.connectivity);
+        let lowest = neighbors.iter().map(|(_, h)| *h).min();
+
+        match lowest {
+            Some(lowest) if lowest < height => {
+                let mut lowest_neighbors: Vec<Coord> = neighbors
+                    .iter()
+                    .filter(|(_, h)| *h == lowest)
+                    .map(|(c, _)| *c)
+                    .collect();
+
+                if lowest_neighbors.len() == 1 {
+                    Step::Downhill(lowest_neighbors.remove(0))
+                } else {
+                    lowest_neighbors.sort_by_key(|c| (c.1, c.0));
+                    Step::Tie(lowest_neighbors)
+                }
+            }
+            Some(lowest) if lowest == height => {
+                let mut level_neighbors: Vec<Coord> = neighbors
+                    .iter()
+                    .filter(|(_, h)| *h == height)
+                    .map(|(c, _)| *c)
+                    .collect();
+                level_neighbors.sort_by_key(|c| (c.1, c.0));
+                Step::Plateau(level_neighbors)
+            }
+            _ => Step::Sink,
+        }
+    }
+
+    // Follows steepest descent from every location. Heights strictly drop along the way, so each
+    // path ends; results are memoized so each location is stepped from once.
+    #[allow(dead_code)]
+    pub fn downstream(&self, height_map: &HeightMap) -> HashMap<Coord, Downstream> {
+        let mut downstream = HashMap::new();
+
+        for start in height_map.coords_in_order() {
+            let mut path = vec![];
+            let mut coord = start;
+
+            let end = loop {
+                if let Some(&known) = downstream.get(&coord) {
+                    break known;
+                }
+
+                match self.step(height_map, &coord) {
+                    Step::Ridge => break Downstream::Ridge,
+                    Step::Sink => break Downstream::Sink(coord),
+                    Step::Plateau(_) => break Downstream::Plateau(coord),
+                    Step::Tie(_) => break Downstream::Tie(coord),
+                    Step::Downhill(next) => {
+                        path.push(coord);
+                        coord = next;
+                    }
+                }
+            };
+
+            downstream.insert(coord, end);
+            for coord in path {
+                downstream.insert(coord, end);
+            }
+        }
+
+        downstream
+    }
+
+    // Locations draining into each sink, in reading order. Locations that stall on a plateau or
+    // split at a tie aren't in any.
+    #[allow(dead_code)]
+    pub fn drainage(&self, height_map: &HeightMap) -> BTreeMap<Coord, Vec<Coord>> {
+        let downstream = self.downstream(height_map);
+        let mut drainage: BTreeMap<Coord, Vec<Coord>> = BTreeMap::new();
+
+        for coord in height_map.coords_in_order() {
+            if let Downstream::Sink(sink) = downstream[&coord] {
+                drainage.entry(sink).or_default().push(coord);
+            }
+        }
+
+        drainage
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_test_input;
+
+    fn height_map(rows: &[&str]) -> HeightMap {
+        HeightMap::new(rows.iter().map(|row| row.to_string()).collect())
+    }
+
+    #[test]
+    fn test_step() {
+        let model = FlowModel::default();
+        let height_map = height_map(&["395", "212", "949"]);
+
+        assert_eq!(model.step(&height_map, &Coord(1, 0)), Step::Ridge);
+        assert_eq!(model.step(&height_map, &Coord(1, 1)), Step::Sink);
+        assert_eq!(
+            model.step(&height_map, &Coord(0, 1)),
+            Step::Downhill(Coord(1, 1))
+        );
+        assert_eq!(
+            model.step(&height_map, &Coord(1, 2)),
+            Step::Downhill(Coord(1, 1))
+        );
+
+        let height_map = self::height_map(&["525", "141", "929"]);
+        assert_eq!(
+            model.step(&height_map, &Coord(1, 1)),
+            Step::Tie(vec![Coord(0, 1), Coord(2, 1)])
+        );
+        assert_eq!(model.step(&height_map, &Coord(1, 0)), Step::Sink);
+
+        let height_map = self::height_map(&["33", "34"]);
+        assert_eq!(
+            model.step(&height_map, &Coord(0, 0)),
+            Step::Plateau(vec![Coord(1, 0), Coord(0, 1)])
+        );
+    }
+
+    #[test]
+    fn test_step_diagonal() {
+        let height_map = height_map(&["900", "050", "005"]);
+        let four = FlowModel::default();
+        let eight = FlowModel {
+            connectivity: Connectivity::Eight,
+            ..FlowModel::default()
+        };
+
+        assert_eq!(
+            four.step(&height_map, &Coord(2, 2)),
+            Step::Tie(vec![Coord(2, 1), Coord(1, 2)])
+        );
+        assert_eq!(
+            eight.step(&height_map, &Coord(2, 2)),
+            Step::Tie(vec![Coord(2, 1), Coord(1, 2)])
+        );
+        assert_eq!(
+            eight.step(&height_map, &Coord(1, 1)),
+            Step::Tie(vec![
+                Coord(1, 0),
+                Coord(2, 0),
+                Coord(0, 1),
+                Coord(2, 1),
+                Coord(0, 2),
+                Coord(1, 2)
+            ])
+        );
+
+        let height_map = self::height_map(&["19", "95"]);
+        assert_eq!(four.step(&height_map, &Coord(1, 1)), Step::Sink);
+        assert_eq!(
+            eight.step(&height_map, &Coord(1, 1)),
+            Step::Downhill(Coord(0, 0))
+        );
+    }
+
+    #[test]
+    fn test_ridge_height() {
+        let height_map = height_map(&["171"]);
+        let model = FlowModel {
+            ridge_height: 7,
+            connectivity: Connectivity::Four,
+        };
+
+        assert_eq!(model.step(&height_map, &Coord(1, 0)), Step::Ridge);
+        assert_eq!(
+            model.downstream(&height_map)[&Coord(0, 0)],
+            Downstream::Sink(Coord(0, 0))
+        );
+        assert_eq!(model.drainage(&height_map).len(), 2);
+    }
+
+    #[test]
+    fn test_downstream() {
+        let model = FlowModel::default();
+        let height_map = height_map(&["5432", "6991", "9880"]);
+        let downstream = model.downstream(&height_map);
+
+        assert_eq!(downstream[&Coord(0, 0)], Downstream::Sink(Coord(3, 2)));
+        assert_eq!(downstream[&Coord(1, 1)], Downstream::Ridge);
+        assert_eq!(downstream[&Coord(0, 1)], Downstream::Sink(Coord(3, 2)));
+        // The left 8 has no lower neighbor, only the other 8
+        assert_eq!(downstream[&Coord(1, 2)], Downstream::Plateau(Coord(1, 2)));
+        assert_eq!(downstream[&Coord(2, 2)], Downstream::Sink(Coord(3, 2)));
+    }
+
+    #[test]
+    fn test_drainage_matches_puzzle_basins() {
+        // The puzzle input has no plateaus or ties inside basins that matter for the low points,
+        // so every low point is a sink
+        let height_map = HeightMap::new(get_test_input::<String>());
+        let drainage = FlowModel::default().drainage(&height_map);
+
+        let mut low_points: Vec<Coord> = height_map.low_points().map(|(c, _)| *c).collect();
+        low_points.sort();
+        assert_eq!(drainage.keys().copied().collect::<Vec<_>>(), low_points);
+    }
+}
@@ -1,13 +1,32 @@
 mod data;
+mod flow;
 mod input;
+mod render;
 
 use data::*;
 use input::*;
+use render::*;
 
+// `cargo run -- render` prints the basin map to the terminal; `cargo run -- ppm <path>` writes it
+// as an image
 fn main() {
-    println!("day: 09");
-    println!("  part 1: {}", part_1(get_input()));
-    println!("  part 2: {}", part_2(get_input()));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["render"] => {
+            let height_map = HeightMap::new(get_input());
+            print!("{}", render_ansi(&height_map, &height_map.basins()));
+        }
+        ["ppm", path] => {
+            let height_map = HeightMap::new(get_input());
+            std::fs::write(path, render_ppm(&height_map, &height_map.basins(), 4)).unwrap();
+        }
+        _ => {
+            println!("day: 09");
+            println!("  part 1: {}", part_1(get_input()));
+            println!("  part 2: {}", part_2(get_input()));
+        }
+    }
 }
 
 // Find the low points: lower than any of its adjacent locations (no diagonals). Risk level of a low
@@ -24,20 +43,16 @@ fn part_1(lines: Vec<String>) -> u32 {
 // always be part of exactly one basin. What do you get if you multiply together the sizes of the
 // three largest basins?
 fn part_2(lines: Vec<String>) -> usize {
-    let height_map = HeightMap::new(lines);
-
-    let mut basin_sizes = height_map
-        .low_points()
-        .map(|(coord, _height)| height_map.basin_size(coord))
+    let mut basin_sizes = HeightMap::new(lines)
+        .basins()
+        .basins
+        .iter()
+        .map(|basin| basin.size())
         .collect::<Vec<usize>>();
 
     basin_sizes.sort();
 
-    basin_sizes
-        .iter()
-        .rev()
-        .take(3)
-        .fold(1, |total, size| total * size)
+    basin_sizes.iter().rev().take(3).product()
 }
 
 #[cfg(test)]
@@ -0,0 +1,159 @@
+use crate::data::*;
+use std::fmt::Write;
+
+// Distinct, evenly spread colors: hues step by the golden angle
+fn basin_color(id: usize) -> (u8, u8, u8) {
+    let hue = (id as f64 * 137.507_764) % 360.0;
+    hsv_to_rgb(hue, 0.65, 0.95)
+}
+
+fn hsv_to_rgb(hue: f64, saturation: f64, value: f64) -> (u8, u8, u8) {
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match (hue / 60.0) as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    let to_byte = |c: f64| ((c + m) * 255.0).round() as u8;
+    (to_byte(r), to_byte(g), to_byte(b))
+}
+
+// The IDs of the (up to) three largest basins, largest first
+pub fn largest_basins(basin_map: &BasinMap) -> Vec<usize> {
+    let mut basins: Vec<&Basin> = basin_map.basins.iter().collect();
+    basins.sort_by_key(|basin| (std::cmp::Reverse(basin.size()), basin.id));
+    basins.iter().take(3).map(|basin| basin.id).collect()
+}
+
+// The height map as text, each basin in its own 24-bit ANSI color, low points in bold reverse
+// video and ridges dimmed
+pub fn render_ansi(height_map: &HeightMap, basin_map: &BasinMap) -> String {
+    let (width, height) = height_map.dimensions();
+    let mut out = String::new();
+
+    for y in 0..height {
+        for x in 0..width {
+            let coord = Coord(x, y);
+            let digit = height_map.points[&coord];
+
+            match basin_map.labels.get(&coord) {
+                Some(&id) => {
+                    let (r, g, b) = basin_color(id);
+                    let low_point = basin_map.basins[id].low_point == coord;
+                    let style = if low_point { "1;7;" } else { "" };
+                    write!(out, "\x1b[{}38;2;{};{};{}m{}\x1b[0m", style, r, g, b, digit).unwrap();
+                }
+                None => write!(out, "\x1b[2m{}\x1b[0m", digit).unwrap(),
+            }
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+// Binary PPM (P6) image with `scale` x `scale` pixels per location. The three largest basins are
+// drawn in full color, other basins in gray shaded by height, ridges in black, and low points in
+// white.
+pub fn render_ppm(height_map: &HeightMap, basin_map: &BasinMap, scale: usize) -> Vec<u8> {
+    let (width, height) = height_map.dimensions();
+    let largest = largest_basins(basin_map);
+
+    let mut ppm = format!(
+        "P6\n{} {}\n255\n",
+        width as usize * scale,
+        height as usize * scale
+    )
+    .into_bytes();
+
+    for y in 0..height {
+        let mut row = vec![];
+        for x in 0..width {
+            let coord = Coord(x, y);
+            let (r, g, b) = match basin_map.labels.get(&coord) {
+                None => (0, 0, 0),
+                Some(&id) if basin_map.basins[id].low_point == coord => (255, 255, 255),
+                Some(&id) if largest.contains(&id) => basin_color(id),
+                Some(_) => {
+                    let shade = 60 + 15 * height_map.points[&coord] as u8;
+                    (shade, shade, shade)
+                }
+            };
+            for _ in 0..scale {
+                row.extend([r, g, b]);
+            }
+        }
+        for _ in 0..scale {
+            ppm.extend(&row);
+        }
+    }
+
+    ppm
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_test_input;
+
+    #[test]
+    fn test_largest_basins() {
+        let height_map = HeightMap::new(get_test_input::<String>());
+        assert_eq!(largest_basins(&height_map.basins()), vec![2, 1, 3]);
+    }
+
+    #[test]
+    fn test_render_ansi() {
+        let height_map = HeightMap::new(get_test_input::<String>());
+        let rendered = render_ansi(&height_map, &height_map.basins());
+        let lines: Vec<&str> = rendered.lines().collect();
+
+        assert_eq!(lines.len(), 5);
+        // Top-left 2 is in basin 0, the 1 next to it is its low point, then a ridge
+        assert!(lines[0].starts_with(&format!(
+            "\x1b[38;2;{};{};{}m2\x1b[0m\x1b[1;7;38;2;{};{};{}m1\x1b[0m\x1b[2m9\x1b[0m",
+            basin_color(0).0,
+            basin_color(0).1,
+            basin_color(0).2,
+            basin_color(0).0,
+            basin_color(0).1,
+            basin_color(0).2,
+        )));
+        assert_eq!(rendered.matches("\x1b[1;7;").count(), 4);
+    }
+
+    #[test]
+    fn test_render_ppm() {
+        let height_map = HeightMap::new(get_test_input::<String>());
+        let ppm = render_ppm(&height_map, &height_map.basins(), 2);
+        let header = b"P6\n20 10\n255\n";
+
+        assert!(ppm.starts_with(header));
+        assert_eq!(ppm.len(), header.len() + 20 * 10 * 3);
+
+        let pixel = |x: usize, y: usize| {
+            let idx = header.len() + (y * 20 + x) * 3;
+            (ppm[idx], ppm[idx + 1], ppm[idx + 2])
+        };
+        // Basin 0 isn't one of the three largest; (0, 0) has height 2
+        assert_eq!(pixel(0, 0), (90, 90, 90));
+        assert_eq!(pixel(2, 0), (255, 255, 255));
+        assert_eq!(pixel(4, 0), (0, 0, 0));
+        // (4, 2) is in basin 2, the largest
+        assert_eq!(pixel(8, 4), basin_color(2));
+        assert_eq!(pixel(9, 5), basin_color(2));
+    }
+
+    #[test]
+    fn test_basin_colors_distinct() {
+        let colors: Vec<_> = (0..20).map(basin_color).collect();
+        for (idx, color) in colors.iter().enumerate() {
+            assert!(!colors[..idx].contains(color));
+        }
+    }
+}
@@ -1,125 +1,484 @@
+use std::rc::Rc;
 use std::str::FromStr;
 
-#[derive(Eq, PartialEq, Debug)]
-pub struct NavLine {
-    raw: String,
-    first_corrupted_char: Option<char>,
-    stack: Vec<char>,
+// An opening and closing delimiter, and what they're worth when scoring
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct DelimiterPair {
+    pub open: String,
+    pub close: String,
+    // Points for finding this closer where another was expected
+    pub error_points: usize,
+    // Points for needing this closer to complete a line
+    pub completion_points: usize,
 }
 
-impl NavLine {
-    pub fn is_corrupted(&self) -> bool {
-        self.first_corrupted_char.is_some()
+impl DelimiterPair {
+    pub fn new(open: &str, close: &str, error_points: usize, completion_points: usize) -> Self {
+        DelimiterPair {
+            open: open.to_string(),
+            close: close.to_string(),
+            error_points,
+            completion_points,
+        }
     }
+}
 
-    pub fn error_score(&self) -> usize {
-        match self.first_corrupted_char {
-            Some(')') => 3,
-            Some(']') => 57,
-            Some('}') => 1197,
-            Some('>') => 25137,
-            Some(_) => unreachable!(),
-            None => 0,
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub enum Token {
+    // Index into the grammar's pairs
+    Open(usize),
+    Close(usize),
+}
+
+// The delimiters a line may use. Delimiters can be several chars long; where more than one matches,
+// the longest wins.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Grammar {
+    pub pairs: Vec<DelimiterPair>,
+    // If false, any text that isn't a delimiter is an error
+    pub allow_other_text: bool,
+    // What the completion score is multiplied by before adding each closer's points
+    pub completion_multiplier: usize,
+}
+
+impl Grammar {
+    pub fn new(pairs: Vec<DelimiterPair>, allow_other_text: bool) -> Result<Self, String> {
+        let delimiters: Vec<&String> = pairs.iter().flat_map(|p| [&p.open, &p.close]).collect();
+
+        for (idx, delimiter) in delimiters.iter().enumerate() {
+            if delimiter.is_empty() {
+                return Err("empty delimiter".to_string());
+            }
+            if delimiters[..idx].contains(delimiter) {
+                return Err(format!("delimiter used twice: {:?}", delimiter));
+            }
         }
+
+        Ok(Grammar {
+            pairs,
+            allow_other_text,
+            completion_multiplier: 5,
+        })
     }
 
-    // Start with a total score of 0. Then, for each character, multiply the total score by 5 and
-    // then increase the total score by the point value given for the character.
-    pub fn completion_score(&self) -> usize {
+    #[allow(dead_code)]
+    pub fn with_completion_multiplier(mut self, completion_multiplier: usize) -> Self {
+        self.completion_multiplier = completion_multiplier;
+        self
+    }
+
+    // The puzzle's navigation subsystem syntax
+    pub fn navigation() -> Self {
+        Grammar::new(
+            vec![
+                DelimiterPair::new("(", ")", 3, 1),
+                DelimiterPair::new("[", "]", 57, 2),
+                DelimiterPair::new("{", "}", 1197, 3),
+                DelimiterPair::new("<", ">", 25137, 4),
+            ],
+            false,
+        )
+        .unwrap()
+    }
+
+    // The longest delimiter at the start of the given text, and its length in bytes
+    pub fn token_at(&self, s: &str) -> Option<(Token, usize)> {
+        self.pairs
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, pair)| {
+                [
+                    (Token::Open(idx), pair.open.as_str()),
+                    (Token::Close(idx), pair.close.as_str()),
+                ]
+            })
+            .filter(|(_, delimiter)| s.starts_with(delimiter))
+            .max_by_key(|(_, delimiter)| delimiter.len())
+            .map(|(token, delimiter)| (token, delimiter.len()))
+    }
+
+    // Start with a total score of 0. Then, for each character, multiply the total score by 5 (the
+    // grammar's completion multiplier) and then increase the total score by the point value given
+    // for the character.
+    pub fn completion_score(&self, stack: &[Opened]) -> usize {
         // Iterate backwards through the stack, so the order matches how we'd complete the string.
         // For example, if the stack is "<{(", we need ")}>" to complete, in that order.
-        self.stack
+        stack
             .iter()
             .rev()
-            .map(|c| match c {
-                '(' => 1,
-                '[' => 2,
-                '{' => 3,
-                '<' => 4,
-                _ => unreachable!(),
+            .map(|opened| self.pairs[opened.pair].completion_points)
+            .fold(0, |total, char_points| {
+                total * self.completion_multiplier + char_points
             })
-            .fold(0, |total, char_points| total * 5 + char_points)
+    }
+
+    // (token, byte offset) for every delimiter in the line
+    pub fn tokenize(&self, s: &str) -> Result<Vec<(Token, usize)>, String> {
+        let mut tokens = vec![];
+        let mut offset = 0;
+
+        while offset < s.len() {
+            match self.token_at(&s[offset..]) {
+                Some((token, len)) => {
+                    tokens.push((token, offset));
+                    offset += len;
+                }
+                None => {
+                    let c = s[offset..].chars().next().unwrap();
+                    if !self.allow_other_text {
+                        return Err(format!("Unknown char: {}", c));
+                    }
+                    offset += c.len_utf8();
+                }
+            }
+        }
+
+        Ok(tokens)
     }
 }
 
-impl FromStr for NavLine {
-    type Err = String;
+// An opening delimiter and the (1-based, in chars) column it starts at
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct Opened {
+    pub pair: usize,
+    pub column: usize,
+}
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut stack = vec![];
-        let mut first_corrupted_char = None;
-
-        for c in s.chars() {
-            if "([{<".contains(c) {
-                stack.push(c);
-            } else if ")]}>".contains(c) {
-                let opening = stack.pop();
-
-                let expect_c = match opening {
-                    Some('(') => ')',
-                    Some('[') => ']',
-                    Some('{') => '}',
-                    Some('<') => '>',
-                    Some(_) => unreachable!(),
-                    None => {
-                        first_corrupted_char = Some(c);
-                        stack.clear();
+// A closer that doesn't match the innermost open delimiter
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct SyntaxError {
+    // 1-based, in chars
+    pub column: usize,
+    // The pair whose closer was found
+    pub found: usize,
+    // What it should have closed; None if nothing was open
+    pub unclosed: Option<Opened>,
+}
+
+thread_local! {
+    // Shared by every line parsed with FromStr
+    static NAVIGATION: Rc<Grammar> = Rc::new(Grammar::navigation());
+}
+
+// A line and the grammar it was parsed with, which its pair indexes refer to
+#[derive(Eq, PartialEq, Debug)]
+pub struct NavLine {
+    grammar: Rc<Grammar>,
+    raw: String,
+    error: Option<SyntaxError>,
+    // Delimiters still open at the end of the line, or at the error
+    stack: Vec<Opened>,
+}
+
+impl NavLine {
+    pub fn parse(s: &str, grammar: &Rc<Grammar>) -> Result<Self, String> {
+        let mut stack: Vec<Opened> = vec![];
+        let mut error = None;
+
+        // Offsets are in bytes; count chars as we go to get columns
+        let mut column = 1;
+        let mut last_offset = 0;
+
+        for (token, offset) in grammar.tokenize(s)? {
+            column += s[last_offset..offset].chars().count();
+            last_offset = offset;
+
+            match token {
+                Token::Open(pair) => stack.push(Opened { pair, column }),
+                Token::Close(pair) => match stack.last() {
+                    Some(opened) if opened.pair == pair => {
+                        stack.pop();
+                    }
+                    unclosed => {
+                        error = Some(SyntaxError {
+                            column,
+                            found: pair,
+                            unclosed: unclosed.copied(),
+                        });
                         break;
                     }
-                };
-
-                if c != expect_c {
-                    first_corrupted_char = Some(c);
-                    stack.clear();
-                    break;
-                }
-            } else {
-                return Err(format!("Unknown char: {}", c));
+                },
             }
         }
 
         Ok(NavLine {
+            grammar: Rc::clone(grammar),
             raw: s.to_string(),
-            first_corrupted_char,
+            error,
             stack,
         })
     }
+
+    pub fn grammar(&self) -> &Grammar {
+        &self.grammar
+    }
+
+    pub fn raw(&self) -> &str {
+        &self.raw
+    }
+
+    #[allow(dead_code)]
+    pub fn error(&self) -> Option<&SyntaxError> {
+        self.error.as_ref()
+    }
+
+    pub fn is_corrupted(&self) -> bool {
+        self.error.is_some()
+    }
+
+    pub fn error_score(&self) -> usize {
+        self.error
+            .as_ref()
+            .map_or(0, |error| self.grammar.pairs[error.found].error_points)
+    }
+
+    // The closers needed to complete the line, in order
+    #[allow(dead_code)]
+    pub fn completion(&self) -> String {
+        if self.is_corrupted() {
+            return String::new();
+        }
+
+        self.stack
+            .iter()
+            .rev()
+            .map(|opened| self.grammar.pairs[opened.pair].close.as_str())
+            .collect()
+    }
+
+    pub fn completion_score(&self) -> usize {
+        if self.is_corrupted() {
+            return 0;
+        }
+
+        self.grammar.completion_score(&self.stack)
+    }
+}
+
+impl NavLine {
+    // A compiler-style report of the error on this line, if any, e.g.
+    //
+    // error: expected `]`, found `}`
+    //  --> line 3, column 13
+    //   |
+    // 3 | {([(<{}[<>[]}>{[]{[(<()>
+    //   |        -    ^ expected `]`
+    //   |        |
+    //   |        `[` opened here
+    pub fn diagnostic(&self, line_number: usize) -> Option<String> {
+        let grammar = &self.grammar;
+        let error = self.error.as_ref()?;
+        let found = &grammar.pairs[error.found].close;
+        let gutter = " ".repeat(line_number.to_string().len());
+        let pad = |column: usize| " ".repeat(column - 1);
+        let carets = "^".repeat(found.chars().count());
+
+        let mut lines = vec![];
+        match &error.unclosed {
+            Some(opened) => {
+                let open = &grammar.pairs[opened.pair].open;
+                let expected = &grammar.pairs[opened.pair].close;
+                let dashes = "-".repeat(open.chars().count());
+                let between = error.column - opened.column - open.chars().count();
+
+                lines.push(format!("error: expected `{}`, found `{}`", expected, found));
+                lines.push(format!(
+                    "{}--> line {}, column {}",
+                    gutter, line_number, error.column
+                ));
+                lines.push(format!("{} |", gutter));
+                lines.push(format!("{} | {}", line_number, self.raw));
+                lines.push(format!(
+                    "{} | {}{}{}{} expected `{}`",
+                    gutter,
+                    pad(opened.column),
+                    dashes,
+                    " ".repeat(between),
+                    carets,
+                    expected
+                ));
+                lines.push(format!("{} | {}|", gutter, pad(opened.column)));
+                lines.push(format!(
+                    "{} | {}`{}` opened here",
+                    gutter,
+                    pad(opened.column),
+                    open
+                ));
+            }
+            None => {
+                lines.push(format!("error: unexpected `{}`", found));
+                lines.push(format!(
+                    "{}--> line {}, column {}",
+                    gutter, line_number, error.column
+                ));
+                lines.push(format!("{} |", gutter));
+                lines.push(format!("{} | {}", line_number, self.raw));
+                lines.push(format!(
+                    "{} | {}{} nothing is open here",
+                    gutter,
+                    pad(error.column),
+                    carets
+                ));
+            }
+        }
+
+        Some(lines.join("\n"))
+    }
+}
+
+impl FromStr for NavLine {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NAVIGATION.with(|grammar| NavLine::parse(s, grammar))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    // Navigation grammar pair indexes of the delimiters still open
+    fn open_pairs(nav_line: &NavLine) -> String {
+        nav_line
+            .stack
+            .iter()
+            .map(|opened| "([{<".chars().nth(opened.pair).unwrap())
+            .collect()
+    }
+
     #[test]
     fn test_nav_line_from_str() {
-        let s = "[({(<(())[]>[[{[]{<()<>>";
+        let nav_line: NavLine = "[({(<(())[]>[[{[]{<()<>>".parse().unwrap();
+        assert_eq!(nav_line.error(), None);
+        assert_eq!(open_pairs(&nav_line), "[({([[{{");
+        assert_eq!(nav_line.stack[0], Opened { pair: 1, column: 1 });
         assert_eq!(
-            s.parse(),
-            Ok(NavLine {
-                raw: s.to_string(),
-                first_corrupted_char: None,
-                stack: "[({([[{{".chars().collect(),
-            })
+            nav_line.stack[7],
+            Opened {
+                pair: 2,
+                column: 18
+            }
         );
 
-        let s = "[(()[<>])]({[<{<<[]>>(";
+        let nav_line: NavLine = "[(()[<>])]({[<{<<[]>>(".parse().unwrap();
+        assert_eq!(nav_line.error(), None);
+        assert_eq!(open_pairs(&nav_line), "({[<{(");
+
+        let nav_line: NavLine = "{([(<{}[<>[]}>{[]{[(<()>".parse().unwrap();
         assert_eq!(
-            s.parse(),
-            Ok(NavLine {
-                raw: s.to_string(),
-                first_corrupted_char: None,
-                stack: "({[<{(".chars().collect(),
+            nav_line.error(),
+            Some(&SyntaxError {
+                column: 13,
+                found: 2,
+                unclosed: Some(Opened { pair: 1, column: 8 }),
             })
         );
 
-        let s = "{([(<{}[<>[]}>{[]{[(<()>";
+        assert!("(a)".parse::<NavLine>().is_err());
+    }
+
+    #[test]
+    fn test_diagnostic() {
+        let nav_line: NavLine = "{([(<{}[<>[]}>{[]{[(<()>".parse().unwrap();
         assert_eq!(
-            s.parse(),
-            Ok(NavLine {
-                raw: s.to_string(),
-                first_corrupted_char: Some('}'),
-                stack: vec![],
-            })
+            nav_line.diagnostic(3).unwrap(),
+            "\
+error: expected `]`, found `}`
+ --> line 3, column 13
+  |
+3 | {([(<{}[<>[]}>{[]{[(<()>
+  |        -    ^ expected `]`
+  |        |
+  |        `[` opened here"
+        );
+
+        let nav_line: NavLine = "()>".parse().unwrap();
+        assert_eq!(
+            nav_line.diagnostic(10).unwrap(),
+            "\
+error: unexpected `>`
+  --> line 10, column 3
+   |
+10 | ()>
+   |   ^ nothing is open here"
+        );
+
+        let nav_line: NavLine = "[({".parse().unwrap();
+        assert_eq!(nav_line.diagnostic(1), None);
+    }
+
+    #[test]
+    fn test_diagnostic_multi_char_delimiters() {
+        let grammar = Rc::new(
+            Grammar::new(
+                vec![
+                    DelimiterPair::new("/*", "*/", 100, 1),
+                    DelimiterPair::new("{", "}", 10, 2),
+                ],
+                true,
+            )
+            .unwrap(),
+        );
+
+        let nav_line = NavLine::parse("é /* { */", &grammar).unwrap();
+        assert_eq!(
+            nav_line.diagnostic(1).unwrap(),
+            "\
+error: expected `}`, found `*/`
+ --> line 1, column 8
+  |
+1 | é /* { */
+  |      - ^^ expected `}`
+  |      |
+  |      `{` opened here"
         );
     }
+
+    #[test]
+    fn test_nav_line_scores() {
+        let nav_line: NavLine = "{([(<{}[<>[]}>{[]{[(<()>".parse().unwrap();
+        assert_eq!(nav_line.error_score(), 1197);
+
+        let nav_line: NavLine = "<{([{{}}[<[[[<>{}]]]>[]]".parse().unwrap();
+        assert_eq!(nav_line.completion(), "])}>");
+        assert_eq!(nav_line.completion_score(), 294);
+    }
+
+    #[test]
+    fn test_custom_grammar() {
+        let grammar = Rc::new(
+            Grammar::new(
+                vec![
+                    DelimiterPair::new("/*", "*/", 100, 1),
+                    DelimiterPair::new("{", "}", 10, 2),
+                    DelimiterPair::new("begin", "end", 1, 3),
+                ],
+                true,
+            )
+            .unwrap()
+            .with_completion_multiplier(10),
+        );
+
+        let nav_line = NavLine::parse("begin { a = 1 /* one */ } b = {", &grammar).unwrap();
+        assert!(!nav_line.is_corrupted());
+        assert_eq!(nav_line.completion(), "}end");
+        assert_eq!(nav_line.completion_score(), 2 * 10 + 3);
+
+        let nav_line = NavLine::parse("{ /* } */", &grammar).unwrap();
+        assert!(nav_line.is_corrupted());
+        assert_eq!(nav_line.error_score(), 10);
+
+        // Longest match: "/**" is "/*" then "*", not a stray "*/"
+        assert_eq!(
+            grammar.tokenize("/**/").unwrap(),
+            vec![(Token::Open(0), 0), (Token::Close(0), 2)]
+        );
+    }
+
+    #[test]
+    fn test_grammar_validation() {
+        assert!(Grammar::new(vec![DelimiterPair::new("|", "|", 1, 1)], false).is_err());
+        assert!(Grammar::new(vec![DelimiterPair::new("", ")", 1, 1)], false).is_err());
+    }
 }
@@ -1,13 +1,49 @@
 mod data;
 mod input;
+mod repair;
+mod stream;
 
 use data::*;
 use input::*;
+use std::fs::File;
+use stream::*;
 
+// `cargo run -- check` prints a diagnostic for every corrupted line; `cargo run -- repair` prints
+// every corrupted line with the fewest edits that balance it; `cargo run -- stream <path>` scores a
+// file of any size without reading it all into memory
 fn main() {
-    println!("day: 10");
-    println!("  part 1: {}", part_1(get_input()));
-    println!("  part 2: {}", part_2(get_input()));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["check"] => {
+            for (idx, nav_line) in get_input::<NavLine>().iter().enumerate() {
+                if let Some(diagnostic) = nav_line.diagnostic(idx + 1) {
+                    println!("{}\n", diagnostic);
+                }
+            }
+        }
+        ["repair"] => {
+            for nav_line in get_input::<NavLine>() {
+                if nav_line.is_corrupted() {
+                    let repair = nav_line.repair();
+                    println!("{} ({} edits)", repair.fixed, repair.edits.len());
+                }
+            }
+        }
+        ["stream", path] => {
+            let grammar = Grammar::navigation();
+            let (error_score, completion_score) = File::open(path)
+                .and_then(|file| stream_scores(file, &grammar))
+                .unwrap();
+            println!("  syntax error score: {}", error_score);
+            println!("  middle completion score: {}", completion_score);
+        }
+        _ => {
+            println!("day: 10");
+            println!("  part 1: {}", part_1(get_input()));
+            println!("  part 2: {}", part_2(get_input()));
+        }
+    }
 }
 
 // Find the first illegal character in each corrupted line of the navigation subsystem. What is the
@@ -0,0 +1,349 @@
+use crate::data::*;
+
+// A single change to a line. Offsets are in bytes into the original line.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum Edit {
+    Insert {
+        offset: usize,
+        text: String,
+    },
+    Delete {
+        offset: usize,
+        text: String,
+    },
+    Substitute {
+        offset: usize,
+        from: String,
+        to: String,
+    },
+}
+
+// A balanced version of a line, and the edits that produce it
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Repair {
+    pub fixed: String,
+    pub edits: Vec<Edit>,
+}
+
+// Edits, then direction flips (an opener substituted for a closer or vice versa), then insertions
+// and deletions. Ties in the edit count are broken by the rest, so the repair stays close to what
+// the line was trying to say.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug, Default)]
+struct Cost(usize, usize, usize);
+
+impl std::ops::Add for Cost {
+    type Output = Cost;
+
+    fn add(self, other: Cost) -> Cost {
+        Cost(self.0 + other.0, self.1 + other.1, self.2 + other.2)
+    }
+}
+
+const INSERT_OR_DELETE: Cost = Cost(1, 0, 1);
+
+// How the first token of a range of tokens is dealt with
+#[derive(Copy, Clone, Debug)]
+enum Choice {
+    // The range is empty
+    Empty,
+    // Matched with the token at the given index, both made into the given pair
+    Match(usize, usize),
+    // An opener, closed by a closer inserted before the token at the given index
+    InsertClose(usize),
+    // A closer, opened by an opener inserted right before it
+    InsertOpen,
+    Delete,
+}
+
+// What ends up in the fixed line, in order
+enum Output {
+    Keep(usize),
+    Substitute(usize, Token),
+    Delete(usize),
+    Insert(usize, Token),
+}
+
+impl Grammar {
+    fn text(&self, token: Token) -> &str {
+        match token {
+            Token::Open(pair) => &self.pairs[pair].open,
+            Token::Close(pair) => &self.pairs[pair].close,
+        }
+    }
+
+    // The fewest insertions, deletions and substitutions of delimiters that balance the line. Other
+    // text is left as it is. Where several repairs are equally short, substituting is preferred
+    // over inserting, and inserting over deleting.
+    pub fn repair(&self, s: &str) -> Result<Repair, String> {
+        let tokens = self.tokenize(s)?;
+        let n = tokens.len();
+
+        // Cost of making the first token an opener and the second a closer, and the pair to use
+        let match_cost = |open: Token, close: Token| match (open, close) {
+            (Token::Open(a), Token::Close(b)) => (Cost((a != b) as usize, 0, 0), a),
+            (Token::Open(a), Token::Open(_)) => (Cost(1, 1, 0), a),
+            (Token::Close(_), Token::Close(b)) => (Cost(1, 1, 0), b),
+            (Token::Close(a), Token::Open(_)) => (Cost(2, 2, 0), a),
+        };
+
+        // cost[i][j] is the cheapest way to balance tokens[i..j]
+        let mut cost = vec![vec![Cost::default(); n + 1]; n + 1];
+        let mut choice = vec![vec![Choice::Empty; n + 1]; n + 1];
+
+        for len in 1..=n {
+            for i in 0..=n - len {
+                let j = i + len;
+                let mut best = (Cost(usize::MAX, 0, 0), Choice::Empty);
+                let mut consider = |c: Cost, ch: Choice| {
+                    if c < best.0 {
+                        best = (c, ch);
+                    }
+                };
+
+                for k in i + 1..j {
+                    let (c, pair) = match_cost(tokens[i].0, tokens[k].0);
+                    consider(c + cost[i + 1][k] + cost[k + 1][j], Choice::Match(k, pair));
+                }
+                match tokens[i].0 {
+                    Token::Open(_) => {
+                        for k in (i + 1..=j).rev() {
+                            consider(
+                                INSERT_OR_DELETE + cost[i + 1][k] + cost[k][j],
+                                Choice::InsertClose(k),
+                            );
+                        }
+                    }
+                    Token::Close(_) => {
+                        consider(INSERT_OR_DELETE + cost[i + 1][j], Choice::InsertOpen)
+                    }
+                }
+                consider(INSERT_OR_DELETE + cost[i + 1][j], Choice::Delete);
+
+                cost[i][j] = best.0;
+                choice[i][j] = best.1;
+            }
+        }
+
+        let mut output = vec![];
+        let offset = |idx: usize| tokens.get(idx).map_or(s.len(), |&(_, offset)| offset);
+        self.trace(&tokens, &choice, 0, n, &offset, &mut output);
+
+        let mut repair = Repair {
+            fixed: String::new(),
+            edits: vec![],
+        };
+        let mut copied = 0;
+
+        for out in output {
+            match out {
+                Output::Keep(idx) => {
+                    let (token, at) = tokens[idx];
+                    repair.fixed.push_str(&s[copied..at]);
+                    repair.fixed.push_str(self.text(token));
+                    copied = at + self.text(token).len();
+                }
+                Output::Substitute(idx, to) => {
+                    let (from, at) = tokens[idx];
+                    repair.fixed.push_str(&s[copied..at]);
+                    repair.fixed.push_str(self.text(to));
+                    copied = at + self.text(from).len();
+                    repair.edits.push(Edit::Substitute {
+                        offset: at,
+                        from: self.text(from).to_string(),
+                        to: self.text(to).to_string(),
+                    });
+                }
+                Output::Delete(idx) => {
+                    let (token, at) = tokens[idx];
+                    repair.fixed.push_str(&s[copied..at]);
+                    copied = at + self.text(token).len();
+                    repair.edits.push(Edit::Delete {
+                        offset: at,
+                        text: self.text(token).to_string(),
+                    });
+                }
+                Output::Insert(at, token) => {
+                    repair.fixed.push_str(&s[copied..at]);
+                    repair.fixed.push_str(self.text(token));
+                    copied = at;
+                    repair.edits.push(Edit::Insert {
+                        offset: at,
+                        text: self.text(token).to_string(),
+                    });
+                }
+            }
+        }
+        repair.fixed.push_str(&s[copied..]);
+
+        Ok(repair)
+    }
+
+    // Walks the chosen repair of tokens[i..j], pushing what ends up in the line in order
+    fn trace(
+        &self,
+        tokens: &[(Token, usize)],
+        choice: &[Vec<Choice>],
+        i: usize,
+        j: usize,
+        offset: &dyn Fn(usize) -> usize,
+        output: &mut Vec<Output>,
+    ) {
+        let keep_or_substitute = |idx: usize, to: Token| {
+            if tokens[idx].0 == to {
+                Output::Keep(idx)
+            } else {
+                Output::Substitute(idx, to)
+            }
+        };
+
+        match choice[i][j] {
+            Choice::Empty => {}
+            Choice::Match(k, pair) => {
+                output.push(keep_or_substitute(i, Token::Open(pair)));
+                self.trace(tokens, choice, i + 1, k, offset, output);
+                output.push(keep_or_substitute(k, Token::Close(pair)));
+                self.trace(tokens, choice, k + 1, j, offset, output);
+            }
+            Choice::InsertClose(k) => {
+                let Token::Open(pair) = tokens[i].0 else {
+                    unreachable!()
+                };
+                output.push(Output::Keep(i));
+                self.trace(tokens, choice, i + 1, k, offset, output);
+                output.push(Output::Insert(offset(k), Token::Close(pair)));
+                self.trace(tokens, choice, k, j, offset, output);
+            }
+            Choice::InsertOpen => {
+                let Token::Close(pair) = tokens[i].0 else {
+                    unreachable!()
+                };
+                output.push(Output::Insert(offset(i), Token::Open(pair)));
+                output.push(Output::Keep(i));
+                self.trace(tokens, choice, i + 1, j, offset, output);
+            }
+            Choice::Delete => {
+                output.push(Output::Delete(i));
+                self.trace(tokens, choice, i + 1, j, offset, output);
+            }
+        }
+    }
+}
+
+impl NavLine {
+    pub fn repair(&self) -> Repair {
+        // The line was already tokenized when it was parsed
+        self.grammar().repair(self.raw()).unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::rc::Rc;
+
+    fn is_balanced(s: &str, grammar: &Rc<Grammar>) -> bool {
+        let nav_line = NavLine::parse(s, grammar).unwrap();
+        !nav_line.is_corrupted() && nav_line.completion().is_empty()
+    }
+
+    #[test]
+    fn test_repair_small() {
+        let grammar = Grammar::navigation();
+
+        assert_eq!(
+            grammar.repair("(]").unwrap(),
+            Repair {
+                fixed: "()".to_string(),
+                edits: vec![Edit::Substitute {
+                    offset: 1,
+                    from: "]".to_string(),
+                    to: ")".to_string()
+                }],
+            }
+        );
+
+        assert_eq!(
+            grammar.repair("[(])").unwrap(),
+            Repair {
+                fixed: "[()]".to_string(),
+                edits: vec![
+                    Edit::Substitute {
+                        offset: 2,
+                        from: "]".to_string(),
+                        to: ")".to_string()
+                    },
+                    Edit::Substitute {
+                        offset: 3,
+                        from: ")".to_string(),
+                        to: "]".to_string()
+                    },
+                ],
+            }
+        );
+
+        assert_eq!(
+            grammar.repair("<>)").unwrap(),
+            Repair {
+                fixed: "<>()".to_string(),
+                edits: vec![Edit::Insert {
+                    offset: 2,
+                    text: "(".to_string()
+                }],
+            }
+        );
+
+        assert_eq!(grammar.repair("").unwrap().edits, vec![]);
+        assert_eq!(grammar.repair("{<>}").unwrap().fixed, "{<>}");
+    }
+
+    #[test]
+    fn test_repair_example() {
+        let grammar = Rc::new(Grammar::navigation());
+
+        for line in crate::get_test_input::<String>() {
+            let repair = grammar.repair(&line).unwrap();
+            assert!(is_balanced(&repair.fixed, &grammar), "{}", repair.fixed);
+
+            let nav_line = NavLine::parse(&line, &grammar).unwrap();
+            if !nav_line.is_corrupted() {
+                // Completing is never worse than the repair
+                assert!(repair.edits.len() <= nav_line.completion().len());
+            }
+        }
+
+        let repair = grammar.repair("{([(<{}[<>[]}>{[]{[(<()>").unwrap();
+        assert_eq!(repair.edits.len(), 5);
+        assert!(is_balanced(&repair.fixed, &grammar));
+    }
+
+    #[test]
+    fn test_repair_multi_char_delimiters() {
+        let grammar = Grammar::new(
+            vec![
+                DelimiterPair::new("/*", "*/", 100, 1),
+                DelimiterPair::new("{", "}", 10, 2),
+            ],
+            true,
+        )
+        .unwrap();
+
+        assert_eq!(
+            grammar.repair("f() { /* x } */").unwrap(),
+            Repair {
+                fixed: "f() { /* x */ }".to_string(),
+                edits: vec![
+                    Edit::Substitute {
+                        offset: 11,
+                        from: "}".to_string(),
+                        to: "*/".to_string()
+                    },
+                    Edit::Substitute {
+                        offset: 13,
+                        from: "*/".to_string(),
+                        to: "}".to_string()
+                    },
+                ],
+            }
+        );
+    }
+}
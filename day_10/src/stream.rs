@@ -0,0 +1,408 @@
+use crate::data::*;
+use std::io::{Error, ErrorKind, Read};
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum LineStatus {
+    Complete,
+    Incomplete {
+        completion_score: usize,
+    },
+    Corrupted {
+        error: SyntaxError,
+        error_score: usize,
+    },
+    // Text the grammar doesn't allow
+    Invalid(String),
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct LineReport {
+    // 1-based
+    pub line: usize,
+    pub status: LineStatus,
+}
+
+// Checks input fed in arbitrary chunks, reporting on each line as it ends. Only the delimiters open
+// on the current line are kept, plus whatever of the last chunk might still turn out to be the start
+// of a longer delimiter, so memory doesn't grow with the length of the input. Delimiters can't
+// contain newlines.
+pub struct StreamValidator<'a> {
+    grammar: &'a Grammar,
+    pending: String,
+    line: usize,
+    // 1-based, in chars
+    column: usize,
+    stack: Vec<Opened>,
+    // Set once the current line has gone wrong; the rest of the line is skipped
+    failed: Option<LineStatus>,
+}
+
+impl<'a> StreamValidator<'a> {
+    pub fn new(grammar: &'a Grammar) -> Self {
+        StreamValidator {
+            grammar,
+            pending: String::new(),
+            line: 1,
+            column: 1,
+            stack: vec![],
+            failed: None,
+        }
+    }
+
+    // Reports for the lines ended by this chunk
+    pub fn feed(&mut self, chunk: &str) -> Vec<LineReport> {
+        self.pending.push_str(chunk);
+        self.consume(false)
+    }
+
+    // Report for the last line, if the input didn't end with a newline
+    pub fn finish(mut self) -> Option<LineReport> {
+        self.consume(true);
+        (self.column > 1).then(|| self.end_line())
+    }
+
+    fn consume(&mut self, at_end: bool) -> Vec<LineReport> {
+        let pending = std::mem::take(&mut self.pending);
+        let mut reports = vec![];
+        let mut offset = 0;
+
+        while offset < pending.len() {
+            let rest = &pending[offset..];
+
+            if rest.starts_with('\n') {
+                reports.push(self.end_line());
+                offset += 1;
+                continue;
+            }
+
+            if self.failed.is_none() {
+                // Wait for more input before deciding what this is
+                if !at_end && self.could_grow(rest) {
+                    break;
+                }
+
+                if let Some((token, len)) = self.grammar.token_at(rest) {
+                    self.push_token(token);
+                    self.column += rest[..len].chars().count();
+                    offset += len;
+                    continue;
+                }
+
+                if !self.grammar.allow_other_text {
+                    let c = rest.chars().next().unwrap();
+                    self.failed = Some(LineStatus::Invalid(format!("Unknown char: {}", c)));
+                    self.stack.clear();
+                }
+            }
+
+            let c = rest.chars().next().unwrap();
+            self.column += 1;
+            offset += c.len_utf8();
+        }
+
+        self.pending = pending[offset..].to_string();
+        reports
+    }
+
+    // Whether the given text is the start of a delimiter longer than it
+    fn could_grow(&self, rest: &str) -> bool {
+        self.grammar
+            .pairs
+            .iter()
+            .flat_map(|pair| [&pair.open, &pair.close])
+            .any(|delimiter| delimiter.len() > rest.len() && delimiter.starts_with(rest))
+    }
+
+    fn push_token(&mut self, token: Token) {
+        let column = self.column;
+
+        match token {
+            Token::Open(pair) => self.stack.push(Opened { pair, column }),
+            Token::Close(pair) => match self.stack.last() {
+                Some(opened) if opened.pair == pair => {
+                    self.stack.pop();
+                }
+                unclosed => {
+                    let error = SyntaxError {
+                        column,
+                        found: pair,
+                        unclosed: unclosed.copied(),
+                    };
+                    self.failed = Some(LineStatus::Corrupted {
+                        error,
+                        error_score: self.grammar.pairs[pair].error_points,
+                    });
+                    self.stack.clear();
+                }
+            },
+        }
+    }
+
+    fn end_line(&mut self) -> LineReport {
+        let status = self.failed.take().unwrap_or_else(|| {
+            if self.stack.is_empty() {
+                LineStatus::Complete
+            } else {
+                LineStatus::Incomplete {
+                    completion_score: self.grammar.completion_score(&self.stack),
+                }
+            }
+        });
+        let report = LineReport {
+            line: self.line,
+            status,
+        };
+
+        self.stack.clear();
+        self.line += 1;
+        self.column = 1;
+        report
+    }
+}
+
+// Feeds everything from the reader to the validator in chunks, passing on each line's report. A
+// read can end partway through a char; those bytes are carried over to the next one, and it's an
+// error if the input ends before the char does.
+pub fn validate_reader<R: Read>(
+    mut reader: R,
+    grammar: &Grammar,
+    mut on_report: impl FnMut(LineReport),
+) -> std::io::Result<()> {
+    let mut validator = StreamValidator::new(grammar);
+    let mut buffer = vec![0; 64 * 1024];
+    let mut carried = 0;
+
+    loop {
+        let read = reader.read(&mut buffer[carried..])?;
+        if read == 0 {
+            break;
+        }
+
+        let filled = carried + read;
+        let valid = match std::str::from_utf8(&buffer[..filled]) {
+            Ok(chunk) => chunk.len(),
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(err) => return Err(Error::new(ErrorKind::InvalidData, err)),
+        };
+
+        let chunk = std::str::from_utf8(&buffer[..valid]).unwrap();
+        validator.feed(chunk).into_iter().for_each(&mut on_report);
+
+        buffer.copy_within(valid..filled, 0);
+        carried = filled - valid;
+    }
+
+    if carried > 0 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "input ends partway through a char",
+        ));
+    }
+    validator.finish().into_iter().for_each(&mut on_report);
+    Ok(())
+}
+
+// Total syntax error score and middle completion score, the same as parts 1 and 2. The middle score
+// needs every incomplete line's score, so unlike the rest this takes memory in proportion to the
+// number of incomplete lines.
+pub fn stream_scores<R: Read>(reader: R, grammar: &Grammar) -> std::io::Result<(usize, usize)> {
+    let mut error_score = 0;
+    let mut completion_scores = vec![];
+
+    validate_reader(reader, grammar, |report| match report.status {
+        LineStatus::Corrupted { error_score: s, .. } => error_score += s,
+        LineStatus::Incomplete { completion_score } => completion_scores.push(completion_score),
+        LineStatus::Complete | LineStatus::Invalid(_) => {}
+    })?;
+
+    completion_scores.sort();
+    let middle = completion_scores
+        .get(completion_scores.len() / 2)
+        .copied()
+        .unwrap_or(0);
+    Ok((error_score, middle))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_test_input;
+    use std::rc::Rc;
+
+    fn validate_in_chunks(input: &str, chunk_len: usize, grammar: &Grammar) -> Vec<LineReport> {
+        let mut validator = StreamValidator::new(grammar);
+        let chars: Vec<char> = input.chars().collect();
+
+        let mut reports: Vec<LineReport> = chars
+            .chunks(chunk_len)
+            .flat_map(|chunk| validator.feed(&chunk.iter().collect::<String>()))
+            .collect();
+        reports.extend(validator.finish());
+        reports
+    }
+
+    #[test]
+    fn test_stream_matches_nav_lines() {
+        let grammar = Rc::new(Grammar::navigation());
+        let lines = get_test_input::<String>();
+        let input = lines.join("\n");
+
+        for chunk_len in [1, 3, 7, input.len()] {
+            let reports = validate_in_chunks(&input, chunk_len, &grammar);
+            assert_eq!(reports.len(), lines.len());
+
+            for (report, line) in reports.iter().zip(&lines) {
+                let nav_line = NavLine::parse(line, &grammar).unwrap();
+                let expected = match nav_line.error() {
+                    Some(error) => LineStatus::Corrupted {
+                        error: error.clone(),
+                        error_score: nav_line.error_score(),
+                    },
+                    None => LineStatus::Incomplete {
+                        completion_score: nav_line.completion_score(),
+                    },
+                };
+                assert_eq!(report.status, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_line_ends() {
+        let grammar = Grammar::navigation();
+        let mut validator = StreamValidator::new(&grammar);
+
+        assert_eq!(
+            validator.feed("()\n\n(x)>"),
+            vec![
+                LineReport {
+                    line: 1,
+                    status: LineStatus::Complete,
+                },
+                LineReport {
+                    line: 2,
+                    status: LineStatus::Complete,
+                },
+            ]
+        );
+        assert_eq!(
+            validator.feed("\n"),
+            vec![LineReport {
+                line: 3,
+                status: LineStatus::Invalid("Unknown char: x".to_string()),
+            }]
+        );
+        assert_eq!(validator.finish(), None);
+    }
+
+    #[test]
+    fn test_stream_split_delimiters() {
+        let grammar = Grammar::new(
+            vec![
+                DelimiterPair::new("<", ">", 1, 1),
+                DelimiterPair::new("<<", ">>", 2, 2),
+            ],
+            false,
+        )
+        .unwrap();
+
+        // "<<" must not be read as two "<" just because it arrived in two chunks
+        assert_eq!(
+            validate_in_chunks("<<>>\n<<<>", 1, &grammar),
+            vec![
+                LineReport {
+                    line: 1,
+                    status: LineStatus::Complete,
+                },
+                LineReport {
+                    line: 2,
+                    status: LineStatus::Incomplete {
+                        completion_score: 2,
+                    },
+                },
+            ]
+        );
+    }
+
+    // Hands out at most `step` bytes per read, so multi-byte chars get split across reads
+    struct Trickle<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = self.step.min(buf.len()).min(self.data.len());
+            buf[..len].copy_from_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Ok(len)
+        }
+    }
+
+    fn guillemets() -> Grammar {
+        Grammar::new(
+            vec![
+                DelimiterPair::new("«", "»", 3, 2),
+                DelimiterPair::new("(", ")", 5, 1),
+            ],
+            true,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_stream_reader_split_chars() {
+        let grammar = guillemets();
+        let input = "«é»\n«(é\n(»\n";
+
+        for step in 1..=input.len() {
+            let reader = Trickle {
+                data: input.as_bytes(),
+                step,
+            };
+            let mut reports = vec![];
+            validate_reader(reader, &grammar, |report| reports.push(report)).unwrap();
+
+            let statuses: Vec<_> = reports.into_iter().map(|report| report.status).collect();
+            assert_eq!(statuses[0], LineStatus::Complete);
+            assert_eq!(
+                statuses[1],
+                LineStatus::Incomplete {
+                    completion_score: 5 + 2,
+                }
+            );
+            assert!(matches!(
+                statuses[2],
+                LineStatus::Corrupted { error_score: 3, .. }
+            ));
+
+            let reader = Trickle {
+                data: input.as_bytes(),
+                step,
+            };
+            assert_eq!(stream_scores(reader, &grammar).unwrap(), (3, 7));
+        }
+    }
+
+    #[test]
+    fn test_stream_reader_ends_mid_char() {
+        let grammar = guillemets();
+        let input = "«é»\n«é".as_bytes();
+
+        for step in [1, 2, input.len()] {
+            let reader = Trickle {
+                data: &input[..input.len() - 1],
+                step,
+            };
+            let err = stream_scores(reader, &grammar).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+
+        let reader = Trickle {
+            data: b"(\xff)",
+            step: 1,
+        };
+        let err = stream_scores(reader, &grammar).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,146 @@
+use crate::data::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+// Any octopus that goes over the threshold flashes and resets, so energies stay bounded and a grid
+// always ends up repeating. Steps are numbered the way OctoGrid::step() does.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Cycle {
+    // First step after which the grid is in a state it'll return to
+    pub start: usize,
+    // Steps between repeats
+    pub length: usize,
+    // The step the grid was on when the cycle was looked for, and its flashes so far
+    from_step: usize,
+    from_flashes: usize,
+    // flashes_before[k] is flashes during the first k steps after from_step, up to start + length
+    flashes_before: Vec<usize>,
+}
+
+fn state_hash(energies: &[usize]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    energies.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl OctoGrid {
+    // Steps a copy of this grid until it's back in a state it has been in before. Only hashes of
+    // past states are kept; a matching hash is confirmed by replaying to that step.
+    pub fn find_cycle(&self) -> Cycle {
+        let mut octo_grid = self.clone();
+        let mut seen: HashMap<u64, Vec<usize>> = HashMap::new();
+        let mut flashes_before = vec![0];
+
+        loop {
+            let steps = octo_grid.step() - self.step();
+            let candidates = seen.entry(state_hash(octo_grid.energies())).or_default();
+
+            let repeat_of = candidates.iter().copied().find(|&earlier| {
+                let mut replay = self.clone();
+                (0..earlier).for_each(|_| replay.step_forward());
+                replay.energies() == octo_grid.energies()
+            });
+
+            if let Some(earlier) = repeat_of {
+                return Cycle {
+                    start: self.step() + earlier,
+                    length: steps - earlier,
+                    from_step: self.step(),
+                    from_flashes: self.flashes(),
+                    flashes_before,
+                };
+            }
+            candidates.push(steps);
+
+            octo_grid.step_forward();
+            flashes_before.push(octo_grid.flashes() - self.flashes());
+        }
+    }
+}
+
+impl Cycle {
+    // Total flashes once the given step is done, without simulating up to it. Panics for steps
+    // before the one the cycle was found from.
+    #[allow(dead_code)]
+    pub fn flashes_after(&self, step: usize) -> usize {
+        assert!(step >= self.from_step, "step {} is in the past", step);
+        let steps = step - self.from_step;
+        let start = self.start - self.from_step;
+
+        let flashes = if steps < self.flashes_before.len() {
+            self.flashes_before[steps]
+        } else {
+            let per_cycle = self.flashes_before[start + self.length] - self.flashes_before[start];
+            let cycles = (steps - start) / self.length;
+            let remainder = (steps - start) % self.length;
+
+            self.flashes_before[start + remainder] + cycles * per_cycle
+        };
+
+        self.from_flashes + flashes
+    }
+
+    // The first step with exactly this many flashes, if there ever is one
+    pub fn first_step_with(&self, flashes: usize) -> Option<usize> {
+        self.flashes_before
+            .windows(2)
+            .position(|pair| pair[1] - pair[0] == flashes)
+            .map(|steps| self.from_step + steps + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_test_input;
+
+    #[test]
+    fn test_find_cycle() {
+        let octo_grid = get_test_input();
+        let cycle = octo_grid.find_cycle();
+
+        // Once everything flashes together, it keeps doing so every 10 steps
+        assert_eq!(cycle.length, 10);
+        assert!(cycle.start <= 195);
+        assert_eq!(cycle.first_step_with(100), Some(195));
+        assert_eq!(cycle.flashes_after(100), 1656);
+    }
+
+    #[test]
+    fn test_flashes_after_matches_simulation() {
+        for grid in ["00900", "123\n456\n789\n012", "5483143223\n2745854711"] {
+            let mut octo_grid: OctoGrid = grid.parse().unwrap();
+            for _ in 0..3 {
+                octo_grid.step_forward();
+            }
+
+            let cycle = octo_grid.find_cycle();
+            for step in 3..=400 {
+                assert_eq!(cycle.flashes_after(step), octo_grid.flashes());
+                octo_grid.step_forward();
+            }
+        }
+    }
+
+    #[test]
+    fn test_flashes_after_far_future() {
+        let octo_grid = get_test_input();
+        let cycle = octo_grid.find_cycle();
+
+        // From step 195 on, all 100 flash every 10th step
+        let synchronized = cycle.flashes_after(195);
+        assert_eq!(
+            cycle.flashes_after(1_000_000_195),
+            synchronized + 100 * 100_000_000
+        );
+    }
+
+    #[test]
+    fn test_never_synchronizes() {
+        // Neighbours keep nudging each other out of step
+        let cycle = "0123456789".parse::<OctoGrid>().unwrap().find_cycle();
+        assert_eq!((cycle.start, cycle.length), (64, 9));
+        assert_eq!(cycle.first_step_with(10), None);
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
 
 #[derive(Eq, PartialEq, Ord, PartialOrd, Clone, Debug)]
 pub struct Coord {
@@ -6,32 +6,82 @@ pub struct Coord {
     pub x: isize,
 }
 
-#[derive(Eq, PartialEq, Debug)]
+// When an octopus flashes, and what its energy goes back to afterwards
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct FlashRules {
+    // An octopus flashes once its energy is above this
+    pub threshold: usize,
+    // Energy of an octopus after it has flashed
+    pub reset: usize,
+}
+
+impl Default for FlashRules {
+    fn default() -> Self {
+        FlashRules {
+            threshold: 9,
+            reset: 0,
+        }
+    }
+}
+
+#[derive(Eq, PartialEq, Clone, Debug)]
 pub struct OctoGrid {
-    octos: BTreeMap<Coord, usize>,
+    // Row-major
+    energies: Vec<usize>,
+    width: usize,
+    height: usize,
+    rules: FlashRules,
     step: usize,
     flashes: usize,
 }
 
 impl OctoGrid {
-    pub fn new(initial: Vec<usize>) -> OctoGrid {
-        let mut octos = BTreeMap::new();
-
-        for (idx, energy) in initial.into_iter().enumerate() {
-            let coord = Coord {
-                x: idx as isize % 10,
-                y: idx as isize / 10,
-            };
-            octos.insert(coord, energy);
-        }
+    // Energies in row order. Panics if they don't fill a whole number of rows.
+    pub fn new(initial: Vec<usize>, width: usize) -> OctoGrid {
+        assert!(
+            width > 0 && initial.len().is_multiple_of(width),
+            "{} octopuses don't make rows of {}",
+            initial.len(),
+            width
+        );
 
         OctoGrid {
-            octos,
+            width,
+            height: initial.len() / width,
+            energies: initial,
+            rules: FlashRules::default(),
             step: 0,
             flashes: 0,
         }
     }
 
+    #[allow(dead_code)]
+    pub fn with_rules(mut self, rules: FlashRules) -> OctoGrid {
+        self.rules = rules;
+        self
+    }
+
+    #[allow(dead_code)]
+    pub fn dimensions(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn octopus_count(&self) -> usize {
+        self.energies.len()
+    }
+
+    #[allow(dead_code)]
+    pub fn energy(&self, coord: &Coord) -> Option<usize> {
+        let in_bounds = (0..self.width as isize).contains(&coord.x)
+            && (0..self.height as isize).contains(&coord.y);
+        in_bounds.then(|| self.energies[coord.y as usize * self.width + coord.x as usize])
+    }
+
+    // Every energy, in row order
+    pub fn energies(&self) -> &[usize] {
+        &self.energies
+    }
+
     pub fn flashes(&self) -> usize {
         self.flashes
     }
@@ -43,62 +93,160 @@ impl OctoGrid {
     // Increase each energy level by 1. Then, any octopus with energy level >9 flashes. This
     // increases the energy level of all adjacent octopuses by 1, including diagonals. If this
     // causes an octopus to have an energy level greater than 9, it also flashes. Any octopus that
-    // flashed during this step has its energy level set to 0.
+    // flashed during this step has its energy level set to 0. (9 and 0 are the default rules.)
     pub fn step_forward(&mut self) {
-        for (_coord, energy) in self.octos.iter_mut() {
+        self.cascade();
+    }
+
+    // Steps forward, returning every octopus that flashed in the order they did, and where each
+    // wave of the cascade ends in that list. Wave 0 is the octopi pushed over the threshold by the
+    // step itself; wave n + 1 is those pushed over by wave n's flashes.
+    fn cascade(&mut self) -> (Vec<usize>, Vec<usize>) {
+        let threshold = self.rules.threshold;
+
+        // Octopi to flash, in the order they went over the threshold. Energy only goes up one at a
+        // time, so an octopus is added exactly when its energy becomes threshold + 1, or up front if
+        // it was already past that.
+        let mut flashing: Vec<usize> = vec![];
+        for (idx, energy) in self.energies.iter_mut().enumerate() {
             *energy += 1;
+            if *energy > threshold {
+                flashing.push(idx);
+            }
         }
 
-        // Flash any octopi with over 9 energy (which increases adjacent energies). Keep doing this
-        // until a steady state is reached.
-        let mut flashed: BTreeSet<Coord> = BTreeSet::new();
-        loop {
-            let to_flash: Vec<_> = self
-                .octos
-                .clone()
-                .into_iter()
-                .filter(|(coord, energy)| *energy > 9 && !flashed.contains(coord))
-                .collect();
+        let mut wave_ends = vec![];
+        let mut next = 0;
+        while next < flashing.len() {
+            if wave_ends.last().map_or(next == 0, |&end| next == end) {
+                wave_ends.push(flashing.len());
+            }
+
+            let idx = flashing[next];
+            next += 1;
 
-            if !to_flash.is_empty() {
-                for (coord, _energy) in to_flash {
-                    self.increase_energy_around(&coord);
-                    flashed.insert(coord);
+            for adjacent in self.adjacent(idx) {
+                self.energies[adjacent] += 1;
+                if self.energies[adjacent] == threshold + 1 {
+                    flashing.push(adjacent);
                 }
-            } else {
-                break;
             }
         }
 
-        // Reset any octopus that flashed to 0 energy
-        for coord in flashed.iter() {
-            *self.octos.get_mut(coord).unwrap() = 0;
+        // Reset any octopus that flashed
+        for &idx in flashing.iter() {
+            self.energies[idx] = self.rules.reset;
         }
 
         self.step += 1;
-        self.flashes += flashed.len();
+        self.flashes += flashing.len();
+        (flashing, wave_ends)
     }
 
-    fn increase_energy_around(&mut self, coord: &Coord) {
-        for dx in -1..=1 {
-            for dy in -1..=1 {
-                // Skip increasing the given Coord's energy
-                if dx == 0 && dy == 0 {
-                    continue;
-                }
+    // Steps this grid forward in place, yielding what flashed during each step
+    #[allow(dead_code)]
+    pub fn flash_events(&mut self) -> FlashEvents<'_> {
+        FlashEvents { octo_grid: self }
+    }
+
+    fn coord_of(&self, idx: usize) -> Coord {
+        Coord {
+            x: (idx % self.width) as isize,
+            y: (idx / self.width) as isize,
+        }
+    }
+
+    // Indexes of the (up to 8) octopi around the given one, including diagonals
+    fn adjacent(&self, idx: usize) -> impl Iterator<Item = usize> {
+        let (width, height) = (self.width as isize, self.height as isize);
+        let (x, y) = ((idx % self.width) as isize, (idx / self.width) as isize);
+
+        (-1..=1)
+            .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+            .filter(|&(dx, dy)| dx != 0 || dy != 0)
+            .map(move |(dx, dy)| (x + dx, y + dy))
+            .filter(move |&(x, y)| (0..width).contains(&x) && (0..height).contains(&y))
+            .map(move |(x, y)| (y * width + x) as usize)
+    }
+}
+
+// The octopi that flashed during one step, wave by wave
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct StepFlashes {
+    // The step just done, numbered the way OctoGrid::step() does
+    pub step: usize,
+    pub waves: Vec<Vec<Coord>>,
+}
+
+impl StepFlashes {
+    pub fn count(&self) -> usize {
+        self.waves.iter().map(Vec::len).sum()
+    }
+
+    #[allow(dead_code)]
+    pub fn flashed(&self, coord: &Coord) -> bool {
+        self.waves.iter().any(|wave| wave.contains(coord))
+    }
+}
+
+pub struct FlashEvents<'a> {
+    octo_grid: &'a mut OctoGrid,
+}
+
+impl Iterator for FlashEvents<'_> {
+    type Item = StepFlashes;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (flashing, wave_ends) = self.octo_grid.cascade();
+
+        let mut waves = vec![];
+        let mut start = 0;
+        for end in wave_ends {
+            waves.push(
+                flashing[start..end]
+                    .iter()
+                    .map(|&idx| self.octo_grid.coord_of(idx))
+                    .collect(),
+            );
+            start = end;
+        }
+
+        Some(StepFlashes {
+            step: self.octo_grid.step(),
+            waves,
+        })
+    }
+}
+
+// Rows of digits, all the same length
+impl FromStr for OctoGrid {
+    type Err = String;
 
-                let adjacent_coord = Coord {
-                    x: coord.x + dx,
-                    y: coord.y + dy,
-                };
-                self.octos.entry(adjacent_coord).and_modify(|e| *e += 1);
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rows: Vec<&str> = s.trim().split('\n').map(str::trim).collect();
+        let width = rows[0].chars().count();
+        let mut initial = vec![];
+
+        for row in rows.iter() {
+            if row.chars().count() != width {
+                return Err(format!("Row isn't {} long: {}", width, row));
+            }
+            for c in row.chars() {
+                let energy = c.to_digit(10).ok_or(format!("Bad energy: {}", c))?;
+                initial.push(energy as usize);
             }
         }
+
+        if width == 0 {
+            return Err("Empty grid".to_string());
+        }
+        Ok(OctoGrid::new(initial, width))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::get_test_input;
 
     #[test]
@@ -121,4 +269,148 @@ mod tests {
         }
         assert_eq!(octo_grid.flashes(), 1656);
     }
+
+    #[test]
+    fn test_octo_grid_from_str() {
+        let octo_grid: OctoGrid = "123\n456".parse().unwrap();
+        assert_eq!(octo_grid.dimensions(), (3, 2));
+        assert_eq!(octo_grid.octopus_count(), 6);
+        assert_eq!(octo_grid.energy(&Coord { y: 1, x: 0 }), Some(4));
+        assert_eq!(octo_grid.energy(&Coord { y: 0, x: 3 }), None);
+
+        assert!("123\n45".parse::<OctoGrid>().is_err());
+        assert!("12a".parse::<OctoGrid>().is_err());
+        assert!("".parse::<OctoGrid>().is_err());
+    }
+
+    #[test]
+    fn test_octo_grid_non_square() {
+        // One bright octopus in a 5x1 strip: flashing lights up its neighbours, not wrapped cells
+        let mut octo_grid: OctoGrid = "00900".parse().unwrap();
+        octo_grid.step_forward();
+        assert_eq!(octo_grid.flashes(), 1);
+        assert_eq!(octo_grid.energies(), vec![1, 2, 0, 2, 1]);
+    }
+
+    #[test]
+    fn test_octo_grid_rules() {
+        let rules = FlashRules {
+            threshold: 3,
+            reset: 1,
+        };
+        let mut octo_grid = "30\n00".parse::<OctoGrid>().unwrap().with_rules(rules);
+
+        octo_grid.step_forward();
+        assert_eq!(octo_grid.flashes(), 1);
+        assert_eq!(octo_grid.energies(), vec![1, 2, 2, 2]);
+
+        octo_grid.step_forward();
+        assert_eq!(octo_grid.flashes(), 1);
+
+        // Everything hits 4 together, then all reset to 1
+        octo_grid.step_forward();
+        assert_eq!(octo_grid.flashes(), 5);
+        assert_eq!(octo_grid.energies(), vec![1, 1, 1, 1]);
+    }
+
+    #[test]
+    fn test_flash_events() {
+        let mut octo_grid: OctoGrid = "00900\n00000".parse().unwrap();
+        let mut events = octo_grid.flash_events();
+
+        // 9 goes to 10 and flashes alone, pushing its neighbours to 2
+        let first = events.next().unwrap();
+        assert_eq!(first.step, 1);
+        assert_eq!(first.waves, vec![vec![Coord { y: 0, x: 2 }]]);
+
+        // Nothing reaches 10 until step 9, when the 2s flash and set off everything else
+        let ninth = events.nth(7).unwrap();
+        assert_eq!(ninth.step, 9);
+        assert_eq!(ninth.waves.len(), 2);
+        assert_eq!(
+            ninth.waves[0],
+            vec![
+                Coord { y: 0, x: 1 },
+                Coord { y: 0, x: 3 },
+                Coord { y: 1, x: 1 },
+                Coord { y: 1, x: 2 },
+                Coord { y: 1, x: 3 },
+            ]
+        );
+        assert!(ninth.flashed(&Coord { y: 0, x: 0 }));
+        assert!(ninth.flashed(&Coord { y: 0, x: 2 }));
+        assert_eq!(ninth.count(), 10);
+        assert_eq!(octo_grid.flashes(), 1 + ninth.count());
+    }
+
+    #[test]
+    fn test_flash_events_match_flashes() {
+        let mut octo_grid = get_test_input();
+        let counts: Vec<usize> = octo_grid
+            .flash_events()
+            .take(100)
+            .map(|e| e.count())
+            .collect();
+
+        assert_eq!(counts[0], 0);
+        assert_eq!(counts[1], 35);
+        assert_eq!(counts.iter().sum::<usize>(), 1656);
+    }
+
+    // The original cascade: flash in waves until no octopus is left over the threshold
+    fn step_in_waves(energies: &mut [usize], width: usize, rules: FlashRules) -> usize {
+        let height = energies.len() / width;
+        let mut flashed = vec![false; energies.len()];
+        energies.iter_mut().for_each(|energy| *energy += 1);
+
+        loop {
+            let wave: Vec<usize> = (0..energies.len())
+                .filter(|&idx| energies[idx] > rules.threshold && !flashed[idx])
+                .collect();
+            if wave.is_empty() {
+                break;
+            }
+
+            for idx in wave {
+                flashed[idx] = true;
+                let (x, y) = ((idx % width) as isize, (idx / width) as isize);
+                for ay in y - 1..=y + 1 {
+                    for ax in x - 1..=x + 1 {
+                        let in_bounds =
+                            (0..width as isize).contains(&ax) && (0..height as isize).contains(&ay);
+                        if in_bounds && (ax, ay) != (x, y) {
+                            energies[ay as usize * width + ax as usize] += 1;
+                        }
+                    }
+                }
+            }
+        }
+
+        for (energy, _) in energies.iter_mut().zip(&flashed).filter(|(_, &f)| f) {
+            *energy = rules.reset;
+        }
+        flashed.iter().filter(|&&f| f).count()
+    }
+
+    #[test]
+    fn test_step_forward_matches_waves() {
+        let (width, height) = (37, 23);
+        let rules = FlashRules {
+            threshold: 12,
+            reset: 2,
+        };
+
+        // Arbitrary but repeatable starting energies
+        let mut energies: Vec<usize> = (0..width * height).map(|idx| idx * 7919 % 13).collect();
+        let mut octo_grid = OctoGrid::new(energies.clone(), width).with_rules(rules);
+        let mut flashes = 0;
+
+        for _ in 0..200 {
+            flashes += step_in_waves(&mut energies, width, rules);
+            octo_grid.step_forward();
+            assert_eq!(octo_grid.energies(), energies);
+            assert_eq!(octo_grid.flashes(), flashes);
+        }
+        assert!(flashes > 0);
+    }
 }
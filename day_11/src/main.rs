@@ -1,13 +1,36 @@
+mod cycle;
 mod data;
 mod input;
+mod render;
 
 use data::*;
 use input::*;
+use render::*;
 
+// `cargo run -- animate [steps]` replays the steps in the terminal, highlighting flashes
 fn main() {
-    println!("day: 11");
-    println!("  part 1: {}", part_1(get_input()));
-    println!("  part 2: {}", part_2(get_input()));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["animate"] => animate(part_2(get_input()) + 10),
+        ["animate", steps] => animate(steps.parse().unwrap()),
+        _ => {
+            println!("day: 11");
+            println!("  part 1: {}", part_1(get_input()));
+            println!("  part 2: {}", part_2(get_input()));
+        }
+    }
+}
+
+fn animate(steps: usize) {
+    let mut octo_grid = get_input();
+
+    for _ in 0..steps {
+        let step_flashes = octo_grid.flash_events().next().unwrap();
+        // Clear the screen and draw from the top left
+        print!("\x1b[2J\x1b[H{}", render_frame(&octo_grid, &step_flashes));
+        std::thread::sleep(std::time::Duration::from_millis(80));
+    }
 }
 
 // How many total flashes are there after 100 steps?
@@ -20,17 +43,11 @@ fn part_1(mut octo_grid: OctoGrid) -> usize {
 }
 
 // What is the first step during which all octopuses flash?
-fn part_2(mut octo_grid: OctoGrid) -> usize {
-    let mut prev_flash_count = 0;
-
-    loop {
-        octo_grid.step_forward();
-        if octo_grid.flashes() == prev_flash_count + 100 {
-            return octo_grid.step();
-        } else {
-            prev_flash_count = octo_grid.flashes();
-        }
-    }
+fn part_2(octo_grid: OctoGrid) -> usize {
+    octo_grid
+        .find_cycle()
+        .first_step_with(octo_grid.octopus_count())
+        .expect("the octopuses never all flash at once")
 }
 
 #[cfg(test)]
@@ -0,0 +1,69 @@
+use crate::data::*;
+use std::fmt::Write;
+
+// One frame of the animation: the grid after a step as text, octopi that just flashed in bold
+// reverse video (brightest for the first wave, dimmer for later ones) and the rest in gray shaded
+// by energy
+pub fn render_frame(octo_grid: &OctoGrid, step_flashes: &StepFlashes) -> String {
+    let (width, height) = octo_grid.dimensions();
+    let max_energy = octo_grid
+        .energies()
+        .iter()
+        .max()
+        .copied()
+        .unwrap_or(0)
+        .max(1);
+    let mut out = format!(
+        "step {}: {} flashed\n",
+        step_flashes.step,
+        step_flashes.count()
+    );
+
+    for y in 0..height as isize {
+        for x in 0..width as isize {
+            let coord = Coord { y, x };
+            let energy = octo_grid.energy(&coord).unwrap();
+
+            match step_flashes
+                .waves
+                .iter()
+                .position(|wave| wave.contains(&coord))
+            {
+                Some(wave) => {
+                    let glow = 255 - (wave * 24).min(128) as u8;
+                    write!(out, "\x1b[1;7;38;2;{};{};0m{}\x1b[0m", glow, glow, energy).unwrap();
+                }
+                None => {
+                    let gray = 64 + (energy * 128 / max_energy) as u8;
+                    write!(
+                        out,
+                        "\x1b[38;2;{};{};{}m{}\x1b[0m",
+                        gray, gray, gray, energy
+                    )
+                    .unwrap();
+                }
+            }
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_frame() {
+        let mut octo_grid: OctoGrid = "09\n00".parse().unwrap();
+        let step_flashes = octo_grid.flash_events().next().unwrap();
+
+        assert_eq!(
+            render_frame(&octo_grid, &step_flashes),
+            "step 1: 1 flashed\n\
+             \x1b[38;2;192;192;192m2\x1b[0m\x1b[1;7;38;2;255;255;0m0\x1b[0m\n\
+             \x1b[38;2;192;192;192m2\x1b[0m\x1b[38;2;192;192;192m2\x1b[0m\n"
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::collections::{BTreeSet, HashMap, HashSet};
+use std::collections::{BTreeSet, HashMap};
 use std::fmt::{Debug, Display, Formatter};
 
 pub type CaveName = String;
@@ -26,60 +26,11 @@ impl CaveSystem {
         CaveSystem { caves }
     }
 
-    pub fn cave<'a>(&self, name: impl Into<CaveName>) -> &Cave {
+    // Panics if there's no such cave. count_paths() and paths() validate first, so a missing start
+    // or end is reported rather than panicking there.
+    pub fn cave(&self, name: impl Into<CaveName>) -> &Cave {
         self.caves.get(&name.into()).unwrap()
     }
-
-    pub fn adjacent_caves<'a>(&self, name: impl Into<CaveName>) -> Vec<&Cave> {
-        self.cave(name)
-            .connected_cave_names
-            .iter()
-            .map(|cn| self.cave(cn))
-            .collect()
-    }
-
-    pub fn path_is_complete(&self, path: &Path) -> bool {
-        path.last() == Some(self.cave("end"))
-    }
-
-    // Small caves can only be visited once
-    pub fn can_visit_next_part_1(&self, path: &Path, cave_name: &CaveName) -> bool {
-        let cave = self.cave(cave_name);
-
-        if cave.is_small && path.contains(cave) {
-            false
-        } else {
-            true
-        }
-    }
-
-    // At most, one small cave can be visited twice; all other small caves can only be visited once.
-    // Also, the start can't be returned to, and once the end is reached, it's over.
-    pub fn can_visit_next_part_2(&self, path: &Path, cave_name: &CaveName) -> bool {
-        let cave = self.cave(cave_name);
-
-        if cave.is_start {
-            false
-        } else if cave.is_small && path.contains(cave) {
-            self.can_visit_a_small_cave_again(path)
-        } else {
-            true
-        }
-    }
-
-    fn can_visit_a_small_cave_again(&self, path: &Path) -> bool {
-        let mut small_caves = HashSet::new();
-
-        for cave in path.iter().filter(|c| c.is_small) {
-            if small_caves.contains(&cave.name) {
-                return false;
-            } else {
-                small_caves.insert(&cave.name);
-            }
-        }
-
-        true
-    }
 }
 
 #[derive(Eq, PartialEq, Ord, PartialOrd, Clone)]
@@ -0,0 +1,59 @@
+use crate::data::*;
+use std::fmt::Write;
+
+// The cave system as a Graphviz graph: start and end as filled double circles, big caves as boxes
+// and small caves as ellipses. Caves and connections are in name order, so the output is stable.
+pub fn to_dot(cave_system: &CaveSystem) -> String {
+    let mut caves: Vec<&Cave> = cave_system.caves.values().collect();
+    caves.sort();
+
+    let mut dot = String::from("graph caves {\n");
+
+    for cave in caves.iter() {
+        let attributes = if cave.is_start {
+            "shape=doublecircle, style=filled, fillcolor=palegreen"
+        } else if cave.is_end {
+            "shape=doublecircle, style=filled, fillcolor=lightcoral"
+        } else if cave.is_big {
+            "shape=box"
+        } else {
+            "shape=ellipse"
+        };
+        writeln!(dot, "    \"{}\" [{}];", cave.name, attributes).unwrap();
+    }
+
+    // Each connection once, from the cave that sorts first
+    for cave in caves.iter() {
+        for name in cave.connected_cave_names.range(cave.name.clone()..) {
+            writeln!(dot, "    \"{}\" -- \"{}\";", cave.name, name).unwrap();
+        }
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_dot() {
+        let lines = ["start-A", "A-b", "b-end", "A-end"];
+        let cave_system = CaveSystem::new(lines.iter().map(|l| l.to_string()).collect());
+
+        assert_eq!(
+            to_dot(&cave_system),
+            "graph caves {\n\
+             \x20   \"A\" [shape=box];\n\
+             \x20   \"b\" [shape=ellipse];\n\
+             \x20   \"end\" [shape=doublecircle, style=filled, fillcolor=lightcoral];\n\
+             \x20   \"start\" [shape=doublecircle, style=filled, fillcolor=palegreen];\n\
+             \x20   \"A\" -- \"b\";\n\
+             \x20   \"A\" -- \"end\";\n\
+             \x20   \"A\" -- \"start\";\n\
+             \x20   \"b\" -- \"end\";\n\
+             }\n"
+        );
+    }
+}
@@ -1,68 +1,52 @@
 mod data;
+mod dot;
 mod input;
+mod paths;
+mod policy;
+mod validate;
 
 use data::*;
+use dot::*;
 use input::*;
-use std::collections::BTreeSet;
+use policy::*;
 
+// `cargo run -- check` lists any problems with the cave system; `cargo run -- dot <path>` writes it
+// out as a Graphviz graph
 fn main() {
-    println!("day: 12");
-    println!("  part 1: {}", part_1(get_input()));
-    println!("  part 2: {}", part_2(get_input()));
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.iter().map(String::as_str).collect::<Vec<_>>()[..] {
+        ["check"] => match CaveSystem::new(get_input()).validate() {
+            Ok(()) => println!("no problems"),
+            Err(problems) => problems.iter().for_each(|problem| println!("{}", problem)),
+        },
+        ["dot", path] => {
+            std::fs::write(path, to_dot(&CaveSystem::new(get_input()))).unwrap();
+        }
+        _ => {
+            println!("day: 12");
+            println!("  part 1: {}", part_1(get_input()));
+            println!("  part 2: {}", part_2(get_input()));
+        }
+    }
 }
 
 // Find the number of distinct paths that start at start, end at end, and don't visit small caves
 // more than once
 fn part_1(lines: Vec<String>) -> usize {
-    let cave_system = CaveSystem::new(lines);
-    let start = cave_system.cave("start");
-    part_1_paths(&cave_system, &start, vec![start.clone()]).len()
+    CaveSystem::new(lines)
+        .count_paths(&SmallCavesOnce)
+        .unwrap()
+        .unwrap()
 }
 
+// A single small cave can be visited at most twice, and the remaining small caves can be visited at
+// most once. The caves named start and end can only be visited exactly once each.
 fn part_2(lines: Vec<String>) -> usize {
-    let cave_system = CaveSystem::new(lines);
-    let start = cave_system.cave("start");
-    part_2_paths(&cave_system, &start, vec![start.clone()]).len()
-}
-
-fn part_1_paths(cave_system: &CaveSystem, cave: &Cave, path: Path) -> BTreeSet<Path> {
-    let mut paths = BTreeSet::new();
-
-    // If this Path is complete, return it early (no need to search backwards)
-    if cave_system.path_is_complete(&path) {
-        paths.insert(path);
-        return paths;
-    }
-
-    for adj_cave in cave_system.adjacent_caves(&cave.name) {
-        if cave_system.can_visit_next_part_1(&path, &adj_cave.name) {
-            let mut new_path = path.clone();
-            new_path.push((*adj_cave).clone());
-            paths.append(&mut part_1_paths(cave_system, adj_cave, new_path));
-        }
-    }
-
-    paths
-}
-
-fn part_2_paths(cave_system: &CaveSystem, cave: &Cave, path: Path) -> BTreeSet<Path> {
-    let mut paths = BTreeSet::new();
-
-    // If this Path is complete, return it early (no need to search backwards)
-    if cave_system.path_is_complete(&path) {
-        paths.insert(path);
-        return paths;
-    }
-
-    for adj_cave in cave_system.adjacent_caves(&cave.name) {
-        if cave_system.can_visit_next_part_2(&path, &adj_cave.name) {
-            let mut new_path = path.clone();
-            new_path.push((*adj_cave).clone());
-            paths.append(&mut part_2_paths(cave_system, adj_cave, new_path));
-        }
-    }
-
-    paths
+    CaveSystem::new(lines)
+        .count_paths(&SmallCaveRevisits(1))
+        .unwrap()
+        .unwrap()
 }
 
 #[cfg(test)]
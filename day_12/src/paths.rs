@@ -0,0 +1,366 @@
+use crate::data::*;
+use crate::policy::*;
+use crate::validate::*;
+use std::collections::{BTreeSet, HashMap};
+
+// The cave system with caves numbered and a visit policy's rules looked up ahead of time, so paths
+// can be followed without building them
+struct NumberedCaves<'a> {
+    caves: Vec<&'a Cave>,
+    adjacent: Vec<Vec<usize>>,
+    limit: Vec<Option<usize>>,
+    // (shift, mask) of each limited cave's visit count in PathState::visits
+    counter: Vec<(u32, u64)>,
+    can_revisit: Vec<bool>,
+    revisits: u32,
+    start: usize,
+    end: usize,
+}
+
+// Where a path has got to, and everything about how it got there that decides where it can go next.
+// Kept small, since it's hashed for every step counted.
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
+struct PathState {
+    cave: u32,
+    // Times each cave with a limit has been entered, packed together and only counted up to the
+    // limit; past that it's only the revisits that matter
+    visits: u64,
+    revisits_used: u32,
+}
+
+impl<'a> NumberedCaves<'a> {
+    // Fails if the visit counts don't fit in a PathState
+    fn new(
+        cave_system: &'a CaveSystem,
+        policy: &impl VisitPolicy,
+    ) -> Result<NumberedCaves<'a>, CaveProblem> {
+        let mut caves: Vec<&Cave> = cave_system.caves.values().collect();
+        caves.sort();
+        let number: HashMap<&CaveName, usize> = caves
+            .iter()
+            .enumerate()
+            .map(|(n, cave)| (&cave.name, n))
+            .collect();
+        let limit: Vec<Option<usize>> = caves.iter().map(|cave| policy.limit(cave)).collect();
+
+        // Each count needs enough bits to get up to its cave's limit
+        let mut bits = 0;
+        let counter = limit
+            .iter()
+            .map(|&limit| {
+                let width = limit.map_or(0, |limit| usize::BITS - limit.leading_zeros());
+                let field = (bits, u64::MAX.checked_shr(u64::BITS - width).unwrap_or(0));
+                bits += width;
+                field
+            })
+            .collect();
+        if bits > u64::BITS {
+            return Err(CaveProblem::TooManyLimitedCaves(bits));
+        }
+
+        Ok(NumberedCaves {
+            adjacent: caves
+                .iter()
+                .map(|cave| {
+                    cave.connected_cave_names
+                        .iter()
+                        .map(|cn| number[cn])
+                        .collect()
+                })
+                .collect(),
+            limit,
+            counter,
+            can_revisit: caves.iter().map(|cave| policy.can_revisit(cave)).collect(),
+            // No path could use more revisits than that anyway
+            revisits: policy.revisits().try_into().unwrap_or(u32::MAX),
+            start: number[&"start".to_string()],
+            end: number[&"end".to_string()],
+            caves,
+        })
+    }
+
+    // The state after entering the given cave, if the policy allows it
+    fn enter(&self, state: PathState, cave: usize) -> Option<PathState> {
+        let mut next = PathState {
+            cave: cave as u32,
+            ..state
+        };
+
+        if let Some(limit) = self.limit[cave] {
+            if limit == 0 {
+                return None;
+            }
+            let (shift, mask) = self.counter[cave];
+            if ((state.visits >> shift) & mask) as usize == limit {
+                if !self.can_revisit[cave] || state.revisits_used == self.revisits {
+                    return None;
+                }
+                next.revisits_used += 1;
+            } else {
+                next.visits += 1 << shift;
+            }
+        }
+
+        Some(next)
+    }
+
+    fn start_state(&self) -> Option<PathState> {
+        let empty = PathState {
+            cave: self.start as u32,
+            visits: 0,
+            revisits_used: 0,
+        };
+        self.enter(empty, self.start)
+    }
+
+    fn moves(&self, state: PathState) -> impl Iterator<Item = PathState> + '_ {
+        self.adjacent[state.cave as usize]
+            .iter()
+            .filter_map(move |&next| self.enter(state, next))
+    }
+
+    // Paths from here to the end, or None if there are too many to count in a usize. Memoized on
+    // the whole state, since nothing else about the path so far matters.
+    fn count_from(&self, state: PathState, memo: &mut HashMap<PathState, usize>) -> Option<usize> {
+        if state.cave as usize == self.end {
+            return Some(1);
+        }
+        if let Some(&count) = memo.get(&state) {
+            return Some(count);
+        }
+
+        let count = self.moves(state).try_fold(0usize, |total, next| {
+            total.checked_add(self.count_from(next, memo)?)
+        })?;
+
+        memo.insert(state, count);
+        Some(count)
+    }
+
+    fn paths_from(&self, state: PathState, path: &mut Vec<usize>, paths: &mut BTreeSet<Path>) {
+        path.push(state.cave as usize);
+
+        if state.cave as usize == self.end {
+            paths.insert(path.iter().map(|&n| self.caves[n].clone()).collect());
+        } else {
+            for next in self.moves(state) {
+                self.paths_from(next, path, paths);
+            }
+        }
+
+        path.pop();
+    }
+}
+
+impl CaveSystem {
+    // Number of paths from start to end that the policy allows, or None if there are more than fit
+    // in a usize, once the cave system is checked for problems that stop them being counted. Once
+    // a path reaches the end, it's over.
+    pub fn count_paths(
+        &self,
+        policy: &impl VisitPolicy,
+    ) -> Result<Option<usize>, Vec<CaveProblem>> {
+        self.validate_for(policy)?;
+
+        let caves = NumberedCaves::new(self, policy).map_err(|problem| vec![problem])?;
+        Ok(caves.start_state().map_or(Some(0), |start| {
+            caves.count_from(start, &mut HashMap::new())
+        }))
+    }
+
+    // Every path count_paths counts. Only worth it when the paths themselves are needed; there can
+    // be a great many.
+    #[allow(dead_code)]
+    pub fn paths(&self, policy: &impl VisitPolicy) -> Result<BTreeSet<Path>, Vec<CaveProblem>> {
+        self.validate_for(policy)?;
+
+        let caves = NumberedCaves::new(self, policy).map_err(|problem| vec![problem])?;
+        let mut paths = BTreeSet::new();
+
+        if let Some(start) = caves.start_state() {
+            caves.paths_from(start, &mut vec![], &mut paths);
+        }
+        Ok(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_test_input;
+
+    #[test]
+    fn test_count_paths_matches_paths() {
+        for input in 1..=3 {
+            let cave_system = CaveSystem::new(get_test_input(input));
+
+            assert_eq!(
+                cave_system.count_paths(&SmallCavesOnce),
+                Ok(Some(cave_system.paths(&SmallCavesOnce).unwrap().len()))
+            );
+            assert_eq!(
+                cave_system.count_paths(&SmallCaveRevisits(1)),
+                Ok(Some(
+                    cave_system.paths(&SmallCaveRevisits(1)).unwrap().len()
+                ))
+            );
+        }
+    }
+
+    #[test]
+    fn test_paths() {
+        let cave_system = CaveSystem::new(get_test_input(1));
+        let paths = cave_system.paths(&SmallCavesOnce).unwrap();
+
+        fn names(path: &Path) -> Vec<&str> {
+            path.iter().map(|c| c.name.as_str()).collect()
+        }
+        assert!(paths
+            .iter()
+            .any(|p| names(p) == ["start", "A", "b", "A", "c", "A", "end"]));
+        assert!(paths.iter().all(|p| names(p)[0] == "start"));
+    }
+
+    // start and end hang off a big cave A, with `small` small caves hanging off A too
+    fn star(small: usize) -> CaveSystem {
+        let mut lines = vec!["start-A".to_string(), "A-end".to_string()];
+        lines.extend((0..small).map(|n| format!("A-s{}", n)));
+        CaveSystem::new(lines)
+    }
+
+    #[test]
+    fn test_count_paths_large() {
+        let small = star(5);
+        assert_eq!(
+            small.count_paths(&SmallCavesOnce),
+            Ok(Some(small.paths(&SmallCavesOnce).unwrap().len()))
+        );
+        assert_eq!(
+            small.count_paths(&SmallCaveRevisits(1)),
+            Ok(Some(small.paths(&SmallCaveRevisits(1)).unwrap().len()))
+        );
+
+        // Any ordered selection of the 12 small caves: sum of 12!/(12-k)! over k
+        let large = star(12);
+        assert_eq!(large.count_paths(&SmallCavesOnce), Ok(Some(1_302_061_345)));
+        let revisits = large.count_paths(&SmallCaveRevisits(1)).unwrap();
+        assert!(revisits > large.count_paths(&SmallCavesOnce).unwrap());
+    }
+
+    #[test]
+    fn test_count_paths_overflow() {
+        // Any interleaving of up to 40 visits each to s0 and s1, which is more than a usize holds
+        let limits = |limit| CaveLimits {
+            limits: HashMap::from([("s0".to_string(), limit), ("s1".to_string(), limit)]),
+            revisits: 0,
+        };
+        let cave_system = star(2);
+
+        assert_eq!(cave_system.count_paths(&limits(2)), Ok(Some(19)));
+        assert_eq!(cave_system.count_paths(&limits(40)), Ok(None));
+    }
+
+    #[test]
+    fn test_high_limits() {
+        // s0 any number of times up to 300, in and out of A each time
+        let limits = CaveLimits {
+            limits: HashMap::from([("s0".to_string(), 300)]),
+            revisits: 0,
+        };
+        assert_eq!(star(1).count_paths(&limits), Ok(Some(301)));
+    }
+
+    #[test]
+    fn test_too_many_small_caves() {
+        // A bit for each small cave, plus start and end
+        assert_eq!(
+            star(64).count_paths(&SmallCavesOnce),
+            Err(vec![CaveProblem::TooManyLimitedCaves(66)])
+        );
+    }
+
+    // Every path, found the slow way: try each step and check the visit counts along the path
+    fn naive_count(
+        cave_system: &CaveSystem,
+        policy: &impl VisitPolicy,
+        path: &mut Vec<CaveName>,
+    ) -> usize {
+        let here = cave_system.cave(path.last().unwrap());
+        if here.is_end {
+            return 1;
+        }
+
+        let mut count = 0;
+        for name in here.connected_cave_names.iter() {
+            path.push(name.clone());
+
+            let mut revisits = 0;
+            let mut allowed = true;
+            for cave in cave_system.caves.values() {
+                let visits = path.iter().filter(|&n| n == &cave.name).count();
+                if let Some(limit) = policy.limit(cave) {
+                    if visits > limit {
+                        allowed &= limit > 0 && policy.can_revisit(cave);
+                        revisits += visits - limit;
+                    }
+                }
+            }
+
+            if allowed && revisits <= policy.revisits() {
+                count += naive_count(cave_system, policy, path);
+            }
+            path.pop();
+        }
+        count
+    }
+
+    fn check_against_naive(cave_system: &CaveSystem, policy: &impl VisitPolicy) -> usize {
+        let count = cave_system.count_paths(policy).unwrap().unwrap();
+        assert_eq!(
+            count,
+            naive_count(cave_system, policy, &mut vec!["start".into()])
+        );
+        assert_eq!(count, cave_system.paths(policy).unwrap().len());
+        count
+    }
+
+    #[test]
+    fn test_revisit_policies() {
+        let cave_system = CaveSystem::new(get_test_input(1));
+
+        assert_eq!(check_against_naive(&cave_system, &SmallCaveRevisits(0)), 10);
+        assert_eq!(check_against_naive(&cave_system, &SmallCaveRevisits(1)), 36);
+        let two = check_against_naive(&cave_system, &SmallCaveRevisits(2));
+        assert!(two > 36);
+
+        // b twice and A at most three times, on top of one spare revisit
+        let limits = CaveLimits {
+            limits: HashMap::from([("b".to_string(), 2), ("A".to_string(), 3)]),
+            revisits: 1,
+        };
+        check_against_naive(&cave_system, &limits);
+    }
+
+    #[test]
+    fn test_forbidden_caves() {
+        let cave_system = CaveSystem::new(get_test_input(1));
+
+        // Without b, paths go through A and c only
+        let no_b = Forbid {
+            caves: BTreeSet::from(["b".to_string()]),
+            policy: SmallCaveRevisits(1),
+        };
+        check_against_naive(&cave_system, &no_b);
+        assert!(cave_system
+            .paths(&no_b)
+            .unwrap()
+            .iter()
+            .all(|path| path.iter().all(|cave| cave.name != "b")));
+
+        let no_start = Forbid {
+            caves: BTreeSet::from(["start".to_string()]),
+            policy: SmallCavesOnce,
+        };
+        assert_eq!(cave_system.count_paths(&no_start), Ok(Some(0)));
+    }
+}
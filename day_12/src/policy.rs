@@ -0,0 +1,88 @@
+use crate::data::*;
+use std::collections::{BTreeSet, HashMap};
+
+// Which caves a path may go through, and how often
+pub trait VisitPolicy {
+    // How many times a path may enter the cave, or None for as often as it likes. Some(0) keeps
+    // paths out of it altogether. Two connected caves (or one connected to itself) can't both be
+    // None, or paths could loop between them forever; count_paths() and paths() check this with
+    // validate_for() and return the loops as problems rather than following them.
+    fn limit(&self, cave: &Cave) -> Option<usize>;
+
+    // Extra visits a path may make, in all, to caves it has already visited up to their limit
+    fn revisits(&self) -> usize {
+        0
+    }
+
+    // Whether an extra visit can be spent on this cave
+    fn can_revisit(&self, cave: &Cave) -> bool {
+        !cave.is_start
+    }
+}
+
+// Small caves can only be visited once
+pub struct SmallCavesOnce;
+
+impl VisitPolicy for SmallCavesOnce {
+    fn limit(&self, cave: &Cave) -> Option<usize> {
+        cave.is_small.then_some(1)
+    }
+}
+
+// Small caves can be visited once, plus this many extra visits to small caves other than start
+pub struct SmallCaveRevisits(pub usize);
+
+impl VisitPolicy for SmallCaveRevisits {
+    fn limit(&self, cave: &Cave) -> Option<usize> {
+        cave.is_small.then_some(1)
+    }
+
+    fn revisits(&self) -> usize {
+        self.0
+    }
+}
+
+// Limits for particular caves; the rest follow SmallCaveRevisits
+#[allow(dead_code)]
+pub struct CaveLimits {
+    pub limits: HashMap<CaveName, usize>,
+    pub revisits: usize,
+}
+
+impl VisitPolicy for CaveLimits {
+    fn limit(&self, cave: &Cave) -> Option<usize> {
+        match self.limits.get(&cave.name) {
+            Some(&limit) => Some(limit),
+            None => SmallCaveRevisits(self.revisits).limit(cave),
+        }
+    }
+
+    fn revisits(&self) -> usize {
+        self.revisits
+    }
+}
+
+// Another policy, with some caves off limits
+#[allow(dead_code)]
+pub struct Forbid<P: VisitPolicy> {
+    pub caves: BTreeSet<CaveName>,
+    pub policy: P,
+}
+
+impl<P: VisitPolicy> VisitPolicy for Forbid<P> {
+    fn limit(&self, cave: &Cave) -> Option<usize> {
+        if self.caves.contains(&cave.name) {
+            Some(0)
+        } else {
+            self.policy.limit(cave)
+        }
+    }
+
+    fn revisits(&self) -> usize {
+        self.policy.revisits()
+    }
+
+    fn can_revisit(&self, cave: &Cave) -> bool {
+        !self.caves.contains(&cave.name) && self.policy.can_revisit(cave)
+    }
+}
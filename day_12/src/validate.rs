@@ -0,0 +1,212 @@
+use crate::data::*;
+use crate::policy::*;
+use std::fmt::{Display, Formatter};
+
+// Something about a cave system that stops paths through it being counted
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum CaveProblem {
+    // There's no cave with this name ("start" or "end")
+    Missing(CaveName),
+    // Two big caves (or one, connected to itself) lead to each other, so there are infinitely many
+    // paths going back and forth between them
+    AdjacentBigCaves(CaveName, CaveName),
+    // The policy lets paths enter both caves (or one, connected to itself) as often as they like,
+    // so there are infinitely many paths going back and forth between them
+    UnlimitedLoop(CaveName, CaveName),
+    // Counting the visits to caves the policy limits takes this many bits, more than the 64 paths
+    // are counted with
+    TooManyLimitedCaves(u32),
+}
+
+impl Display for CaveProblem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaveProblem::Missing(name) => write!(f, "no cave named {}", name),
+            CaveProblem::AdjacentBigCaves(c1, c2) => write!(
+                f,
+                "big caves {} and {} are connected, so paths can loop between them forever",
+                c1, c2
+            ),
+            CaveProblem::UnlimitedLoop(c1, c2) => write!(
+                f,
+                "caves {} and {} are connected and have no visit limit, so paths can loop between \
+                 them forever",
+                c1, c2
+            ),
+            CaveProblem::TooManyLimitedCaves(bits) => write!(
+                f,
+                "visit counts for limited caves need {} bits, more than the 64 there are",
+                bits
+            ),
+        }
+    }
+}
+
+impl CaveSystem {
+    // Every problem with this cave system, in name order
+    pub fn problems(&self) -> Vec<CaveProblem> {
+        let mut problems = vec![];
+
+        for name in ["start", "end"] {
+            if !self.caves.contains_key(name) {
+                problems.push(CaveProblem::Missing(name.into()));
+            }
+        }
+
+        let mut big_caves: Vec<&Cave> = self.caves.values().filter(|c| c.is_big).collect();
+        big_caves.sort();
+        for cave in big_caves {
+            for name in cave.connected_cave_names.range(cave.name.clone()..) {
+                if self.cave(name).is_big {
+                    problems.push(CaveProblem::AdjacentBigCaves(
+                        cave.name.clone(),
+                        name.clone(),
+                    ));
+                }
+            }
+        }
+
+        problems
+    }
+
+    pub fn validate(&self) -> Result<(), Vec<CaveProblem>> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    // The problems that stop paths being counted under the policy, in name order. Rather than
+    // adjacent big caves, it's adjacent caves the policy doesn't limit, big or small, that let
+    // paths loop forever.
+    pub fn validate_for(&self, policy: &impl VisitPolicy) -> Result<(), Vec<CaveProblem>> {
+        let mut problems: Vec<CaveProblem> = self
+            .problems()
+            .into_iter()
+            .filter(|problem| matches!(problem, CaveProblem::Missing(_)))
+            .collect();
+
+        let mut unlimited: Vec<&Cave> = self
+            .caves
+            .values()
+            .filter(|cave| policy.limit(cave).is_none())
+            .collect();
+        unlimited.sort();
+        for cave in unlimited {
+            for name in cave.connected_cave_names.range(cave.name.clone()..) {
+                if policy.limit(self.cave(name.as_str())).is_none() {
+                    problems.push(CaveProblem::UnlimitedLoop(cave.name.clone(), name.clone()));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_test_input;
+    use std::collections::HashMap;
+
+    fn cave_system(lines: &[&str]) -> CaveSystem {
+        CaveSystem::new(lines.iter().map(|&line| line.to_string()).collect())
+    }
+
+    #[test]
+    fn test_validate() {
+        for input in 1..=3 {
+            assert_eq!(CaveSystem::new(get_test_input(input)).validate(), Ok(()));
+        }
+
+        assert_eq!(
+            cave_system(&["start-A", "A-B", "B-end", "C-C", "A-b"]).problems(),
+            vec![
+                CaveProblem::AdjacentBigCaves("A".into(), "B".into()),
+                CaveProblem::AdjacentBigCaves("C".into(), "C".into()),
+            ]
+        );
+
+        assert_eq!(
+            cave_system(&["start-a"]).validate(),
+            Err(vec![CaveProblem::Missing("end".into())])
+        );
+        assert_eq!(
+            cave_system(&[]).problems(),
+            vec![
+                CaveProblem::Missing("start".into()),
+                CaveProblem::Missing("end".into()),
+            ]
+        );
+    }
+
+    // Only start and end are limited
+    struct EndsOnly;
+
+    impl VisitPolicy for EndsOnly {
+        fn limit(&self, cave: &Cave) -> Option<usize> {
+            (cave.is_start || cave.is_end).then_some(1)
+        }
+    }
+
+    #[test]
+    fn test_validate_for() {
+        let loops = cave_system(&["start-A", "A-B", "B-end"]);
+        assert_eq!(
+            loops.count_paths(&SmallCavesOnce),
+            Err(vec![CaveProblem::UnlimitedLoop("A".into(), "B".into())])
+        );
+
+        // With A limited, paths can only go back and forth so often
+        let limits = CaveLimits {
+            limits: HashMap::from([("A".to_string(), 2)]),
+            revisits: 0,
+        };
+        assert_eq!(loops.validate_for(&limits), Ok(()));
+        assert_eq!(loops.count_paths(&limits), Ok(Some(2)));
+
+        // Small caves left unlimited loop just the same, as does a cave connected to itself
+        assert_eq!(
+            cave_system(&["start-a", "a-b", "b-end"]).count_paths(&EndsOnly),
+            Err(vec![CaveProblem::UnlimitedLoop("a".into(), "b".into())])
+        );
+        assert_eq!(
+            cave_system(&["start-a", "a-a", "a-end"]).validate_for(&EndsOnly),
+            Err(vec![CaveProblem::UnlimitedLoop("a".into(), "a".into())])
+        );
+
+        let no_end = cave_system(&["start-A", "A-b"]);
+        assert_eq!(
+            no_end.paths(&SmallCavesOnce),
+            Err(vec![CaveProblem::Missing("end".into())])
+        );
+    }
+
+    #[test]
+    fn test_problem_display() {
+        assert_eq!(
+            CaveProblem::Missing("end".into()).to_string(),
+            "no cave named end"
+        );
+        assert_eq!(
+            CaveProblem::AdjacentBigCaves("A".into(), "B".into()).to_string(),
+            "big caves A and B are connected, so paths can loop between them forever"
+        );
+        assert_eq!(
+            CaveProblem::UnlimitedLoop("a".into(), "b".into()).to_string(),
+            "caves a and b are connected and have no visit limit, so paths can loop between them \
+             forever"
+        );
+        assert_eq!(
+            CaveProblem::TooManyLimitedCaves(66).to_string(),
+            "visit counts for limited caves need 66 bits, more than the 64 there are"
+        );
+    }
+}